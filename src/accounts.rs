@@ -0,0 +1,437 @@
+use std::{collections::{BTreeMap, HashMap}, fmt, path::Path, sync::{Arc, Mutex}};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::{fs, task::JoinSet};
+
+use crate::{DeviceAuth, TwitchClient, builder::TwitchClientBuilder, client_type::ClientType, endpoints::Endpoints, error::{AuthError, SystemError, TwitchError}, rate_limit::RateLimiter, scope::Scope, store::{FileStore, SessionStore}};
+
+/// How a single account of an [`AccountManager`] connects to Twitch.
+#[derive(Debug, Clone, Default)]
+pub struct AccountOptions {
+    /// Proxy the account's requests go through, see [`TwitchClientBuilder::with_proxy`].
+    pub proxy: Option<String>,
+    /// Rate limit of the account's client. Pass the same limiter to several accounts to share it.
+    pub rate_limit: Option<RateLimiter>,
+}
+
+/// Health of an account, as last seen by the [`AccountManager`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", content = "reason", rename_all = "snake_case")]
+pub enum AccountStatus {
+    /// The session was loaded but has not been checked yet.
+    Unchecked,
+    /// The last health check passed.
+    Healthy,
+    /// The account has no token, or it was revoked and could not be renewed.
+    NeedsAuth,
+    /// A device authorization started with [`AccountManager::request_reauth`] is pending.
+    Authorizing,
+    /// The last health check failed for another reason, e.g. an unreachable proxy.
+    Unreachable(String),
+}
+
+/// Snapshot of an account for dashboards.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AccountState {
+    pub name: String,
+    pub status: AccountStatus,
+    pub user_id: Option<String>,
+    pub login: Option<String>,
+    pub device_id: String,
+    pub proxy: Option<String>,
+    pub token_expires_at: Option<DateTime<Utc>>,
+    /// When the last health check finished.
+    pub last_checked: Option<DateTime<Utc>>,
+}
+
+struct Account {
+    client: TwitchClient,
+    store: Arc<dyn SessionStore>,
+    proxy: Option<String>,
+    status: AccountStatus,
+    last_checked: Option<DateTime<Utc>>,
+}
+
+impl Account {
+    fn state(&self, name: &str) -> AccountState {
+        let tokens = self.client.tokens();
+        AccountState {
+            name: name.to_string(),
+            status: self.status.clone(),
+            user_id: self.client.user_id.clone(),
+            login: self.client.login.clone(),
+            device_id: self.client.device_id.clone(),
+            proxy: self.proxy.clone(),
+            token_expires_at: tokens.and_then(|tokens| tokens.expires_at),
+            last_checked: self.last_checked,
+        }
+    }
+}
+
+/// Runs many accounts side by side, each with its own [`TwitchClient`], session store, proxy,
+/// rate limit and device ID.
+///
+/// Clones share the same accounts, so a dashboard can read [`AccountManager::states`] while
+/// other tasks use the clients.
+///
+/// ```rust,no_run
+/// use std::path::Path;
+/// use twitch_gql_rs::{accounts::{AccountManager, AccountOptions, AccountStatus}, client_type::ClientType};
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let manager = AccountManager::new(ClientType::android_app());
+/// manager.load_dir(Path::new("sessions"), |name| AccountOptions {
+///     proxy: Some(format!("socks5://127.0.0.1:{}", if name == "main" { 1080 } else { 1081 })),
+///     ..Default::default()
+/// }).await?;
+///
+/// for state in manager.check_health().await {
+///     if state.status == AccountStatus::NeedsAuth {
+///         let device_auth = manager.request_reauth(&state.name, &[]).await?;
+///         println!("{}: open {} and enter {}", state.name, device_auth.verification_uri, device_auth.user_code);
+///         manager.reauth(&state.name, device_auth).await?;
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct AccountManager {
+    client_type: Arc<ClientType>,
+    endpoints: Option<Endpoints>,
+    accounts: Arc<Mutex<BTreeMap<String, Account>>>,
+}
+
+impl fmt::Debug for AccountManager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AccountManager").field("accounts", &self.names()).finish_non_exhaustive()
+    }
+}
+
+impl AccountManager {
+    /// Creates an empty manager. Accounts without a saved session start as `client_type`.
+    pub fn new(client_type: ClientType) -> Self {
+        AccountManager { client_type: Arc::new(client_type), endpoints: None, accounts: Arc::default() }
+    }
+
+    /// Points every account added afterwards at `endpoints`, e.g. a mock server.
+    pub fn with_endpoints(mut self, endpoints: Endpoints) -> Self {
+        self.endpoints = Some(endpoints);
+        self
+    }
+
+    /// Adds an account whose session is kept in `store`, replacing any account of the same name.
+    ///
+    /// If the store is empty, the account starts a new session and needs [`AccountManager::reauth`].
+    /// Renewed tokens are saved back to the store.
+    pub async fn add(&self, name: &str, store: Arc<dyn SessionStore>, options: AccountOptions) -> Result<(), SystemError> {
+        let mut builder = match TwitchClientBuilder::from_store(store.clone()).await {
+            Ok(builder) => builder,
+            Err(SystemError::SessionNotFound) => TwitchClientBuilder::new(&self.client_type),
+            Err(e) => return Err(e),
+        };
+        if let Some(endpoints) = &self.endpoints {
+            builder = builder.with_endpoints(endpoints.clone());
+        }
+        if let Some(proxy) = &options.proxy {
+            builder = builder.with_proxy(proxy);
+        }
+        let mut client = builder.build()?.with_session_store(store.clone());
+        client.rate_limiter = options.rate_limit;
+        let status = if client.tokens().is_some() { AccountStatus::Unchecked } else { AccountStatus::NeedsAuth };
+        let account = Account { client, store, proxy: options.proxy, status, last_checked: None };
+        self.accounts.lock().unwrap().insert(name.to_string(), account);
+        Ok(())
+    }
+
+    /// Adds every `*.json` session file in `dir` as an account named after the file.
+    ///
+    /// `options` is called with each account name. Returns the number of accounts added.
+    pub async fn load_dir(&self, dir: &Path, options: impl Fn(&str) -> AccountOptions) -> Result<usize, SystemError> {
+        let mut entries = fs::read_dir(dir).await?;
+        let mut paths = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.extension().is_some_and(|extension| extension == "json") && entry.file_type().await?.is_file() {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+        for path in &paths {
+            let name = path.file_stem().unwrap_or_default().to_string_lossy();
+            self.add(&name, Arc::new(FileStore::new(path)), options(&name)).await?;
+        }
+        Ok(paths.len())
+    }
+
+    /// Removes an account and returns its client.
+    pub fn remove(&self, name: &str) -> Option<TwitchClient> {
+        self.accounts.lock().unwrap().remove(name).map(|account| account.client)
+    }
+
+    /// Returns the client of an account. Clones share tokens and rate limits with the original.
+    pub fn client(&self, name: &str) -> Option<TwitchClient> {
+        self.accounts.lock().unwrap().get(name).map(|account| account.client.clone())
+    }
+
+    /// Names of all accounts, in alphabetical order.
+    pub fn names(&self) -> Vec<String> {
+        self.accounts.lock().unwrap().keys().cloned().collect()
+    }
+
+    /// Returns the state of an account.
+    pub fn state(&self, name: &str) -> Option<AccountState> {
+        self.accounts.lock().unwrap().get(name).map(|account| account.state(name))
+    }
+
+    /// Returns the state of every account, in alphabetical order.
+    pub fn states(&self) -> Vec<AccountState> {
+        self.accounts.lock().unwrap().iter().map(|(name, account)| account.state(name)).collect()
+    }
+
+    /// Validates every account's session concurrently and returns the new states.
+    ///
+    /// Revoked or expired tokens are renewed when the session has a refresh token.
+    /// Accounts with a pending device authorization are skipped.
+    pub async fn check_health(&self) -> Vec<AccountState> {
+        let clients: Vec<(String, TwitchClient)> = self.accounts.lock().unwrap().iter()
+            .filter(|(_, account)| account.status != AccountStatus::Authorizing)
+            .map(|(name, account)| (name.clone(), account.client.clone()))
+            .collect();
+        let mut checks = JoinSet::new();
+        let mut names = HashMap::new();
+        for (name, client) in clients {
+            let check = checks.spawn(async move {
+                let result = match client.validate_session().await {
+                    Err(AuthError::InvalidToken) if client.tokens().is_some_and(|tokens| tokens.refresh_token.is_some()) => {
+                        match client.refresh_access_token().await {
+                            Ok(_) => client.validate_session().await,
+                            Err(_) => Err(AuthError::InvalidToken),
+                        }
+                    },
+                    result => result,
+                };
+                result.map_err(|e| match e {
+                    AuthError::NotAuthenticated | AuthError::InvalidToken => AccountStatus::NeedsAuth,
+                    e => AccountStatus::Unreachable(e.to_string()),
+                })
+            });
+            names.insert(check.id(), name);
+        }
+        while let Some(joined) = checks.join_next_with_id().await {
+            // A check that panicked only marks its own account.
+            let (id, result) = match joined {
+                Ok((id, result)) => (id, result),
+                Err(e) => (e.id(), Err(AccountStatus::Unreachable(format!("The health check failed: {}", e)))),
+            };
+            let mut accounts = self.accounts.lock().unwrap();
+            let Some(account) = names.get(&id).and_then(|name| accounts.get_mut(name)) else {
+                continue;
+            };
+            account.last_checked = Some(Utc::now());
+            account.status = match result {
+                Ok(session) => {
+                    account.client.user_id = Some(session.user_id);
+                    account.client.login = Some(session.login);
+                    AccountStatus::Healthy
+                },
+                Err(status) => status,
+            };
+        }
+        self.states()
+    }
+
+    /// Starts a device authorization for an account, see [`TwitchClient::request_device_auth`].
+    pub async fn request_reauth(&self, name: &str, scopes: &[Scope]) -> Result<DeviceAuth, AuthError> {
+        let client = self.client(name).ok_or_else(|| SystemError::UnknownAccount(name.to_string()))?;
+        let device_auth = client.request_device_auth_with_scopes(scopes).await?;
+        self.set_status(name, AccountStatus::Authorizing);
+        Ok(device_auth)
+    }
+
+    /// Waits for the user to authorize `device_auth`, then saves the new session to the account's store.
+    pub async fn reauth(&self, name: &str, device_auth: DeviceAuth) -> Result<(), AuthError> {
+        let mut client = self.client(name).ok_or_else(|| SystemError::UnknownAccount(name.to_string()))?;
+        self.set_status(name, AccountStatus::Authorizing);
+        let result = client.auth(device_auth).await;
+        let mut accounts = self.accounts.lock().unwrap();
+        if let Some(account) = accounts.get_mut(name) {
+            account.last_checked = Some(Utc::now());
+            match &result {
+                Ok(()) => {
+                    account.client = client;
+                    account.status = AccountStatus::Healthy;
+                },
+                Err(_) => account.status = AccountStatus::NeedsAuth,
+            }
+        }
+        result
+    }
+
+    /// Logs an account out, see [`TwitchClient::logout`]. The account stays registered and needs re-authorization.
+    pub async fn logout(&self, name: &str) -> Result<(), AuthError> {
+        let mut client = self.client(name).ok_or_else(|| SystemError::UnknownAccount(name.to_string()))?;
+        let result = client.logout().await;
+        self.finish_logout(name, client, &result);
+        result
+    }
+
+    /// Logs every account out concurrently and returns the result for each account, in alphabetical order.
+    pub async fn logout_all(&self) -> Vec<(String, Result<(), AuthError>)> {
+        let clients: Vec<(String, TwitchClient)> = self.accounts.lock().unwrap().iter()
+            .map(|(name, account)| (name.clone(), account.client.clone()))
+            .collect();
+        let mut logouts = JoinSet::new();
+        let mut names = HashMap::new();
+        for (name, mut client) in clients {
+            let logout = logouts.spawn(async move {
+                let result = client.logout().await;
+                (client, result)
+            });
+            names.insert(logout.id(), name);
+        }
+        let mut results = Vec::new();
+        while let Some(joined) = logouts.join_next_with_id().await {
+            match joined {
+                Ok((id, (client, result))) => {
+                    let name = names[&id].clone();
+                    self.finish_logout(&name, client, &result);
+                    results.push((name, result));
+                },
+                // A logout that panicked only fails its own account.
+                Err(e) => {
+                    let name = names[&e.id()].clone();
+                    let message = format!("The logout failed: {}", e);
+                    self.set_status(&name, AccountStatus::Unreachable(message.clone()));
+                    results.push((name, Err(TwitchError::TwitchError(message).into())));
+                },
+            }
+        }
+        results.sort_by(|a, b| a.0.cmp(&b.0));
+        results
+    }
+
+    fn finish_logout(&self, name: &str, client: TwitchClient, result: &Result<(), AuthError>) {
+        if let Some(account) = self.accounts.lock().unwrap().get_mut(name) {
+            // The tokens are cleared once revoked, even if the store could not be updated.
+            if client.tokens().is_none() {
+                account.client = client;
+                account.status = AccountStatus::NeedsAuth;
+            } else if let Err(e) = result {
+                account.status = AccountStatus::Unreachable(e.to_string());
+            }
+        }
+    }
+
+    /// Writes every account's session to its store.
+    pub async fn save_all(&self) -> Result<(), SystemError> {
+        let accounts: Vec<(TwitchClient, Arc<dyn SessionStore>)> = self.accounts.lock().unwrap().values()
+            .map(|account| (account.client.clone(), account.store.clone()))
+            .collect();
+        for (client, store) in accounts {
+            client.save_to(store.as_ref()).await?;
+        }
+        Ok(())
+    }
+
+    fn set_status(&self, name: &str, status: AccountStatus) {
+        if let Some(account) = self.accounts.lock().unwrap().get_mut(name) {
+            account.status = status;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use super::*;
+    use crate::{mock::MockTwitch, store::{MemoryStore, encode_session}};
+
+    #[tokio::test]
+    async fn account_manager_checks_and_reauthorizes_accounts() -> Result<(), Box<dyn Error>> {
+        let mock = MockTwitch::start().await?;
+        let (alice, bob) = (MemoryStore::new(), MemoryStore::new());
+        mock.authorized_client(&ClientType::android_app()).await?.save_to(&alice).await?;
+
+        let manager = AccountManager::new(ClientType::android_app()).with_endpoints(mock.endpoints());
+        let options = AccountOptions { rate_limit: Some(RateLimiter::new(10.0, 5)), ..Default::default() };
+        manager.add("alice", Arc::new(alice), options).await?;
+        manager.add("bob", Arc::new(bob.clone()), AccountOptions::default()).await?;
+        let states = manager.states();
+        assert_eq!(states.iter().map(|s| (s.name.as_str(), s.status.clone())).collect::<Vec<_>>(), [("alice", AccountStatus::Unchecked), ("bob", AccountStatus::NeedsAuth)]);
+        assert_ne!(states[0].device_id, states[1].device_id);
+        assert!(manager.client("alice").unwrap().rate_limiter.is_some());
+
+        let states = manager.check_health().await;
+        assert_eq!(states.iter().map(|s| s.status.clone()).collect::<Vec<_>>(), [AccountStatus::Healthy, AccountStatus::NeedsAuth]);
+        assert!(states[0].last_checked.is_some());
+
+        let device_auth = manager.request_reauth("bob", &[]).await?;
+        assert_eq!(manager.state("bob").unwrap().status, AccountStatus::Authorizing);
+        manager.reauth("bob", device_auth).await?;
+        let bob_state = manager.state("bob").unwrap();
+        assert_eq!((bob_state.status, bob_state.login.as_deref()), (AccountStatus::Healthy, Some("mock_viewer")));
+        assert!(bob.load().await?.is_some());
+        assert!(matches!(manager.request_reauth("carol", &[]).await, Err(AuthError::SystemError(SystemError::UnknownAccount(_)))));
+        Ok(())
+    }
+
+    /// Holds a session but panics when it is written or deleted.
+    struct PanickingStore(Vec<u8>);
+
+    impl SessionStore for PanickingStore {
+        fn load(&self) -> crate::store::StoreFuture<'_, Option<Vec<u8>>> {
+            Box::pin(async { Ok(Some(self.0.clone())) })
+        }
+
+        fn save<'a>(&'a self, _session: &'a [u8]) -> crate::store::StoreFuture<'a, ()> {
+            panic!("save")
+        }
+
+        fn delete(&self) -> crate::store::StoreFuture<'_, ()> {
+            panic!("delete")
+        }
+    }
+
+    #[tokio::test]
+    async fn a_panicking_account_does_not_hide_the_others() -> Result<(), Box<dyn Error>> {
+        let mock = MockTwitch::start().await?;
+        let session = encode_session(&mock.authorized_client(&ClientType::web()).await?)?;
+        let manager = AccountManager::new(ClientType::web()).with_endpoints(mock.endpoints());
+        manager.add("alice", Arc::new(PanickingStore(session.clone())), AccountOptions::default()).await?;
+        let bob = MemoryStore::new();
+        bob.save(&session).await?;
+        manager.add("bob", Arc::new(bob), AccountOptions::default()).await?;
+
+        // Renewing alice's token saves it to her store, which panics.
+        mock.state().access_token = "rotated".into();
+        let states = manager.check_health().await;
+        assert!(matches!(&states[0].status, AccountStatus::Unreachable(reason) if reason.contains("panicked")));
+        assert_eq!(states[1].status, AccountStatus::Healthy);
+
+        let results = manager.logout_all().await;
+        assert_eq!(results.iter().map(|(name, result)| (name.as_str(), result.is_ok())).collect::<Vec<_>>(), [("alice", false), ("bob", true)]);
+        assert_eq!(manager.state("bob").unwrap().status, AccountStatus::NeedsAuth);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn every_account_is_logged_out() -> Result<(), Box<dyn Error>> {
+        let mock = MockTwitch::start().await?;
+        let client = mock.authorized_client(&ClientType::web()).await?;
+        let manager = AccountManager::new(ClientType::web()).with_endpoints(mock.endpoints());
+        for name in ["alice", "bob"] {
+            let store = MemoryStore::new();
+            store.save(&encode_session(&client)?).await?;
+            manager.add(name, Arc::new(store), AccountOptions::default()).await?;
+        }
+        // Both accounts hold the same mock token: the second revocation finds it already revoked.
+        let results = manager.logout_all().await;
+        assert_eq!(results.iter().map(|(name, result)| (name.as_str(), result.is_ok())).collect::<Vec<_>>(), [("alice", true), ("bob", true)]);
+        assert!(manager.states().iter().all(|state| state.status == AccountStatus::NeedsAuth && state.login.is_none()));
+        assert_eq!(mock.requests().iter().filter(|r| r.path == "/oauth2/revoke").count(), 2);
+        Ok(())
+    }
+}
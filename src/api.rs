@@ -1,15 +1,13 @@
 use std::time::Duration;
 
 use base64::Engine;
+use chrono::{TimeDelta, Utc};
 use regex::Regex;
-use reqwest::Client;
+use reqwest::Method;
 use serde_json::{json, Value};
 use tokio::{sync::watch::{self, Sender}, time::{Instant, sleep}};
 
-use crate::{error::{AuthError, TwitchError}, gql::playback_access_token};
-
-const DEVICE_URL: &'static str = "https://id.twitch.tv/oauth2/device";
-const TOKEN_URL: &'static str = "https://id.twitch.tv/oauth2/token";
+use crate::{TwitchClient, error::{AuthError, TwitchError}, gql::playback_access_token, http::{HttpRequest, send}, scope::Scope, token::{SessionInfo, TokenSet}};
 
 #[derive(Debug, Clone)]
 pub struct DeviceAuth {
@@ -20,111 +18,222 @@ pub struct DeviceAuth {
     expires_in: u64
 }
 
-async fn validate (client: &Client, oauth: &str) -> Result<(String, String), TwitchError> {
-    let url = "https://id.twitch.tv/oauth2/validate";
-    let get_validate = client.get(url).header("Authorization", format!("OAuth {}", oauth)).send().await?;
-    let get_validate: Value = get_validate.json().await?;
-    if let Some(user_id) = get_validate.get("user_id").and_then(|s| s.as_str()) {
-        if let Some(login) = get_validate.get("login").and_then(|s| s.as_str()) {
-            return Ok((user_id.to_string(), login.to_string()));
-        } else {
-            return Err(TwitchError::TwitchError("Not found login".into()));
-        }
-    } else {
-        return Err(TwitchError::TwitchError("Not found user_id".into()));
+pub async fn validate (twitch: &TwitchClient, oauth: &str) -> Result<SessionInfo, AuthError> {
+    let request = HttpRequest::new("oauth2/validate", Method::GET, &twitch.endpoints.oauth_validate).with_header("Authorization", &format!("OAuth {}", oauth));
+    let get_validate = send(twitch, request).await?;
+    if get_validate.status == 401 {
+        return Err(AuthError::InvalidToken);
+    }
+    if !get_validate.is_success() {
+        return Err(TwitchError::HttpError(get_validate.status).into());
     }
+    let get_validate: Value = get_validate.json()?;
+    let user_id = get_validate.get("user_id").and_then(|s| s.as_str()).ok_or_else(|| TwitchError::TwitchError("Not found user_id".into()))?;
+    let login = get_validate.get("login").and_then(|s| s.as_str()).ok_or_else(|| TwitchError::TwitchError("Not found login".into()))?;
+    let client_id = get_validate.get("client_id").and_then(|s| s.as_str()).unwrap_or_default();
+    let scopes = parse_scopes(get_validate.get("scopes"));
+    // Tokens that never expire are reported with `expires_in: 0`.
+    let expires_in = get_validate.get("expires_in").and_then(|s| s.as_u64()).filter(|secs| *secs > 0).map(Duration::from_secs);
+    Ok(SessionInfo { user_id: user_id.to_string(), login: login.to_string(), client_id: client_id.to_string(), scopes, expires_in })
 }
 
-pub async fn request_device_auth (client: &Client, client_id: &str) -> Result<DeviceAuth, TwitchError> {
+fn parse_scopes(scopes: Option<&Value>) -> Vec<Scope> {
+    let scopes = scopes.and_then(|s| s.as_array()).map(Vec::as_slice).unwrap_or_default();
+    scopes.iter().filter_map(|s| s.as_str()).map(|s| { let Ok(scope) = s.parse(); scope }).collect()
+}
+
+pub async fn request_device_auth (twitch: &TwitchClient, scopes: &[Scope]) -> Result<DeviceAuth, TwitchError> {
+    let scopes = scopes.iter().map(Scope::as_str).collect::<Vec<_>>().join(" ");
     let payload = [
-        ("client_id", client_id),
-        ("scopes", "")
+        ("client_id", twitch.client_id.as_str()),
+        ("scopes", &scopes)
     ];
-    let response = client.post(DEVICE_URL).form(&payload).send().await?;
-    if !response.status().is_success() {
-        return Err(TwitchError::HttpError(response.status().as_u16()));
+    let request = HttpRequest::new("oauth2/device", Method::POST, &twitch.endpoints.oauth_device).with_form(&payload);
+    let response = send(twitch, request).await?;
+    if !response.is_success() {
+        return Err(TwitchError::HttpError(response.status));
     }
-    let response: Value = response.json().await?;
+    let response: Value = response.json()?;
     let device_code = response.get("device_code").and_then(|s| s.as_str()).ok_or_else(|| TwitchError::MissingField("device_code".into()))?;
     let user_code = response.get("user_code").and_then(|s| s.as_str()).ok_or_else(|| TwitchError::MissingField("user_code".into()))?;
     let interval = response.get("interval").and_then(|s| s.as_u64()).ok_or_else(|| TwitchError::MissingField("interval".into()))?;
     let verification_uri = response.get("verification_uri").and_then(|s| s.as_str()).ok_or_else(|| TwitchError::MissingField("verification_uri".into()))?;
     let expires_in = response.get("expires_in").and_then(|s| s.as_u64()).ok_or_else(|| TwitchError::MissingField("expires_in".into()))?;
-    Ok(DeviceAuth { device_code: device_code.to_string(), user_code: user_code.to_string(), interval: interval, verification_uri: verification_uri.to_string(), expires_in: expires_in })
+    #[cfg(feature = "tracing")]
+    tracing::debug!(user_code = "[redacted]", interval, expires_in, "device authorization started");
+    Ok(DeviceAuth { device_code: device_code.to_string(), user_code: user_code.to_string(), interval, verification_uri: verification_uri.to_string(), expires_in })
+}
+
+/// Progress of a device authorization poll, reported before every request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeviceAuthProgress {
+    /// Number of the poll about to be sent, starting at 1.
+    pub attempt: u32,
+    /// Current wait between polls. It grows when Twitch asks to slow down.
+    pub interval: Duration,
+    /// Time left before the device code expires.
+    pub remaining: Duration,
 }
 
-pub async fn poll_device_auth (client: &Client, client_id: &str, device_auth: DeviceAuth) -> Result<(String, String, String), AuthError> {
+/// Extra wait added to the polling interval on every `slow_down` answer (RFC 8628, section 3.5).
+const SLOW_DOWN_STEP: Duration = Duration::from_secs(5);
+
+/// OAuth error code of a failed `oauth2/token` response.
+/// Twitch puts it in `message` (e.g. `invalid device code`), RFC 8628 servers in `error`.
+pub fn oauth_error_code(response: &Value) -> Option<String> {
+    const KNOWN: &[&str] = &["authorization_pending", "slow_down", "access_denied", "expired_token", "invalid_device_code"];
+    let codes = ["message", "error"].into_iter()
+        .filter_map(|field| response.get(field).and_then(|v| v.as_str()))
+        .map(|code| code.trim().to_ascii_lowercase().replace(' ', "_"));
+    let codes: Vec<String> = codes.collect();
+    codes.iter().find(|code| KNOWN.contains(&code.as_str())).or(codes.first()).cloned()
+}
+
+pub async fn poll_device_auth (twitch: &TwitchClient, device_auth: DeviceAuth, on_progress: &mut (dyn FnMut(&DeviceAuthProgress) + Send)) -> Result<(TokenSet, String, String), AuthError> {
     let payload = [
-        ("client_id", client_id),
+        ("client_id", twitch.client_id.as_str()),
         ("device_code", &device_auth.device_code),
         ("grant_type", "urn:ietf:params:oauth:grant-type:device_code"),
     ];
-    let expires_in = Instant::now() + Duration::from_secs(device_auth.expires_in);
+    let expires_at = Instant::now() + Duration::from_secs(device_auth.expires_in);
+    let mut interval = Duration::from_secs(device_auth.interval);
+    let mut attempt = 0;
     loop {
-        if Instant::now() >= expires_in {
+        let now = Instant::now();
+        if now >= expires_at {
+            #[cfg(feature = "tracing")]
+            tracing::debug!(poll = attempt, "device code expired");
             return Err(AuthError::DeviceTokenExpired);
         }
-        let status = client.post(TOKEN_URL).form(&payload).send().await?;
-        if status.status().is_success() && !status.status().as_u16() == 400 {
-            return Err(AuthError::TwitchError(TwitchError::HttpError(status.status().as_u16())));
+        attempt += 1;
+        on_progress(&DeviceAuthProgress { attempt, interval, remaining: expires_at - now });
+        let request = HttpRequest::new("oauth2/token", Method::POST, &twitch.endpoints.oauth_token).with_form(&payload);
+        // One span per poll, the `twitch_request` span of the token request nests inside it.
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!("device_auth_poll", attempt, interval_secs = interval.as_secs(), status = tracing::field::Empty);
+        let poll = send(twitch, request);
+        #[cfg(feature = "tracing")]
+        let poll = tracing::Instrument::instrument(poll, span.clone());
+        let response = poll.await?;
+        let body: Value = response.json().unwrap_or_default();
+        #[cfg(feature = "tracing")]
+        {
+            span.record("status", response.status);
+            span.in_scope(|| tracing::debug!("polled device authorization"));
+        }
+        if response.is_success() {
+            let tokens = token_set(&body).ok_or_else(|| TwitchError::MissingField("access_token".into()))?;
+            let session = validate(twitch, &tokens.access_token).await?;
+            return Ok((tokens, session.user_id, session.login));
         }
-        let status: Value = status.json().await?;
-        if let Some(access_token) = status.get("access_token").and_then(|s| s.as_str()) {
-            let user = validate(&client, &access_token).await?;
-            return Ok((access_token.to_string(), user.0, user.1));
-        } else {
-            sleep(Duration::from_secs(device_auth.interval)).await;
+        match oauth_error_code(&body).as_deref() {
+            Some("authorization_pending") => {},
+            Some("slow_down") => interval += SLOW_DOWN_STEP,
+            Some("access_denied") => return Err(AuthError::AccessDenied),
+            Some("expired_token") => return Err(AuthError::DeviceTokenExpired),
+            Some("invalid_device_code") => return Err(AuthError::InvalidDeviceCode),
+            code => return Err(AuthError::OAuth { status: response.status, code: code.unwrap_or_default().to_string() }),
         }
+        sleep(interval).await;
     }
 }
 
-async fn _watch_stream (client: &Client, channel_login: &str) -> Result<(), TwitchError> {
-    let playback = playback_access_token(client, channel_login).await?;
-    let url = format!("https://usher.ttvnw.net/api/channel/hls/{}.m3u8", channel_login);
-    let get_available_qualities = client.get(url).query(&[("sig", &playback.signature), ("token", &playback.value)]).send().await?;
-    let text = get_available_qualities.text().await?;
-    if let Ok(json) = serde_json::from_str::<Value>(&text) {
-        if let Some(error) = json.get(0).and_then(|s| s.get("error")) {
-            return Err(TwitchError::TwitchError(error.to_string()));
-        }
+/// Reads the tokens of a successful `oauth2/token` response.
+pub fn token_set(response: &Value) -> Option<TokenSet> {
+    let access_token = response.get("access_token").and_then(|s| s.as_str())?;
+    let refresh_token = response.get("refresh_token").and_then(|s| s.as_str());
+    let expires_at = response.get("expires_in").and_then(|s| s.as_i64()).map(|secs| Utc::now() + TimeDelta::seconds(secs));
+    let scopes = parse_scopes(response.get("scope"));
+    Some(TokenSet { access_token: access_token.to_string(), refresh_token: refresh_token.map(str::to_string), expires_at, scopes })
+}
+
+pub async fn refresh_access_token (twitch: &TwitchClient, refresh_token: &str) -> Result<TokenSet, TwitchError> {
+    let payload = [
+        ("client_id", twitch.client_id.as_str()),
+        ("grant_type", "refresh_token"),
+        ("refresh_token", refresh_token),
+    ];
+    // A refresh token may only be used once, so the request is never re-sent.
+    let request = HttpRequest::new("oauth2/token", Method::POST, &twitch.endpoints.oauth_token).with_form(&payload).retryable(false);
+    let response = send(twitch, request).await?;
+    let body: Value = response.json().unwrap_or_default();
+    if !response.is_success() {
+        let message = body.get("message").and_then(|m| m.as_str()).unwrap_or("unknown error");
+        return Err(TwitchError::TokenRefreshFailed(format!("{} ({})", message, response.status)));
+    }
+    token_set(&body).ok_or_else(|| TwitchError::MissingField("access_token".into()))
+}
+
+/// Revokes an access token. A token Twitch no longer knows counts as revoked.
+pub async fn revoke_token (twitch: &TwitchClient, access_token: &str) -> Result<(), TwitchError> {
+    let payload = [
+        ("client_id", twitch.client_id.as_str()),
+        ("token", access_token),
+    ];
+    let request = HttpRequest::new("oauth2/revoke", Method::POST, &twitch.endpoints.oauth_revoke).with_form(&payload);
+    let response = send(twitch, request).await?;
+    if response.is_success() {
+        return Ok(());
+    }
+    let body: Value = response.json().unwrap_or_default();
+    let message = body.get("message").and_then(|m| m.as_str()).unwrap_or("unknown error");
+    if response.status == 400 && message.eq_ignore_ascii_case("invalid token") {
+        return Ok(());
+    }
+    Err(TwitchError::TwitchError(format!("Failed to revoke the access token: {} ({})", message, response.status)))
+}
+
+async fn _watch_stream (twitch: &TwitchClient, channel_login: &str) -> Result<(), TwitchError> {
+    let playback = playback_access_token(twitch, channel_login).await?;
+    let url = format!("{}/api/channel/hls/{}.m3u8", twitch.endpoints.usher, channel_login);
+    let request = HttpRequest::new("usher/hls", Method::GET, &url).with_query(&[("sig", &playback.signature), ("token", &playback.value)]);
+    let text = send(twitch, request).await?.body;
+    if let Ok(json) = serde_json::from_str::<Value>(&text) && let Some(error) = json.get(0).and_then(|s| s.get("error")) {
+        return Err(TwitchError::TwitchError(error.to_string()));
     }
-    println!("{}", text);
+    #[cfg(feature = "tracing")]
+    tracing::debug!(channel_login, playlist_bytes = text.len(), "fetched stream playlist");
     Ok(())
 }
 
-async fn _get_spade_url (spade: &str, client: &Client, tx: Sender<String>) -> Result<(), TwitchError> {
+async fn _get_spade_url (spade: &str, twitch: &TwitchClient, tx: Sender<String>) -> Result<(), TwitchError> {
     let settings_pattern = Regex::new(r#"src="(https://[\w.]+/config/settings\.[0-9a-f]{32}\.js)""#).unwrap();
     let spade_pattern = Regex::new(r#""(?:beacon|spade)_?url": ?"(https://[.\w\-/]+)""#).unwrap();
     
-    if let Some(caps) = spade_pattern.captures(&spade) {
+    if let Some(caps) = spade_pattern.captures(spade) {
         let spade_url = caps.get(1).unwrap().as_str();
         tx.send(spade_url.to_string()).unwrap();
         return Ok(());
     }
-    if let Some(caps) = settings_pattern.captures(&spade) {
+    if let Some(caps) = settings_pattern.captures(spade) {
         let settings_url = caps.get(1).unwrap().as_str();
-        let settings_js = client.get(settings_url).send().await?.text().await?;
+        let settings_js = send(twitch, HttpRequest::new("settings", Method::GET, settings_url)).await?.body;
         if let Some(caps2) = spade_pattern.captures(&settings_js) {
             let spade_url = caps2.get(1).unwrap().as_str();
             tx.send(spade_url.to_string()).unwrap();
-            return Ok(());
+            Ok(())
         } else {
-            return Err(TwitchError::TwitchError("Error while spade_url extraction: step #2".into()));
+            Err(TwitchError::TwitchError("Error while spade_url extraction: step #2".into()))
         }
     } else {
-        return Err(TwitchError::TwitchError("Error while spade_url extraction: step #1".into()));
+        Err(TwitchError::TwitchError("Error while spade_url extraction: step #1".into()))
     }
 }
 
-pub async fn _send_watch (client: &Client, user_id: &str, client_url: &str, channel_login: &str, broadcast_id: &str, channel_id: &str) -> Result<(), TwitchError> {
-    let url = format!("{}/{}", client_url, channel_login);
-    let get_spade = client.get(url).send().await?;
-    let spade = get_spade.text().await?;
-    let (tx, mut rx) = watch::channel(String::new());
-    _get_spade_url(&spade, &client, tx).await?;
-    rx.changed().await.unwrap();
-    let spade_url = rx.borrow().clone();
-    drop(rx);
+pub async fn _send_watch (twitch: &TwitchClient, user_id: &str, channel_login: &str, broadcast_id: &str, channel_id: &str) -> Result<(), TwitchError> {
+    let spade_url = match &twitch.endpoints.spade {
+        Some(spade_url) => spade_url.clone(),
+        None => {
+            let url = format!("{}/{}", twitch.client_url, channel_login);
+            let spade = send(twitch, HttpRequest::new("channel_page", Method::GET, &url)).await?.body;
+            let (tx, mut rx) = watch::channel(String::new());
+            _get_spade_url(&spade, twitch, tx).await?;
+            rx.changed().await.unwrap();
+            let spade_url = rx.borrow().clone();
+            drop(rx);
+            spade_url
+        }
+    };
     let payload = json!([
         {
             "event": "minute-watched",
@@ -144,11 +253,11 @@ pub async fn _send_watch (client: &Client, user_id: &str, client_url: &str, chan
     ]);
     let payload = serde_json::to_string(&payload)?;
     let base64 = base64::engine::general_purpose::STANDARD.encode(&payload);
-    let send_watch = client.post(spade_url.to_string()).form(&[("data", base64)]).send().await?;
-    let status = send_watch.status();
-    if status == 204 {
-        return Ok(());
+    let request = HttpRequest::new("spade", Method::POST, &spade_url).with_form(&[("data", &base64)]);
+    let send_watch = send(twitch, request).await?;
+    if send_watch.status == 204 {
+        Ok(())
     } else {
-        return Err(TwitchError::HttpError(status.as_u16()));
+        Err(TwitchError::HttpError(send_watch.status))
     }
 }
\ No newline at end of file
@@ -0,0 +1,248 @@
+use std::{net::{IpAddr, Ipv4Addr, Ipv6Addr}, time::Duration};
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use reqwest::{Method, Url};
+use sha2::{Digest, Sha256};
+use tokio::{io::{AsyncReadExt, AsyncWriteExt}, net::{TcpListener, TcpStream}, task::JoinSet, time::timeout};
+
+use crate::{TwitchClient, api::{oauth_error_code, token_set, validate}, error::{AuthError, SystemError, TwitchError}, http::{HttpRequest, send}, scope::Scope, token::TokenSet};
+
+/// A pending authorization-code login, see [`TwitchClient::request_auth_code`].
+///
+/// Open `authorize_url` in the user's browser. Twitch sends the browser back to `redirect_uri`,
+/// where a temporary listener of this request picks up the code.
+#[derive(Debug)]
+pub struct AuthCodeRequest {
+    pub authorize_url: String,
+    pub redirect_uri: String,
+    listener: TcpListener,
+    path: String,
+    state: String,
+    code_verifier: String,
+}
+
+/// Largest request head accepted by the redirect listener.
+const MAX_REQUEST_HEAD: usize = 16 * 1024;
+
+/// How long a connection to the redirect listener may take to send its request head.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long the user has to log in before [`AuthError::RedirectTimeout`].
+const LOGIN_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+const DONE_PAGE: &str = "<!DOCTYPE html><html><body><p>You are logged in. You can close this window.</p></body></html>";
+const FAILED_PAGE: &str = "<!DOCTYPE html><html><body><p>The login failed. You can close this window.</p></body></html>";
+
+fn random_token() -> String {
+    URL_SAFE_NO_PAD.encode(rand::random::<[u8; 32]>())
+}
+
+pub async fn request_auth_code (twitch: &TwitchClient, redirect_uri: &str, scopes: &[Scope]) -> Result<AuthCodeRequest, AuthError> {
+    let invalid = |reason: &str| AuthError::InvalidRedirectUri(format!("{}: {}", redirect_uri, reason));
+    let mut redirect = Url::parse(redirect_uri).map_err(|e| invalid(&e.to_string()))?;
+    if redirect.scheme() != "http" {
+        return Err(invalid("only http:// redirects can be received locally"));
+    }
+    let ip = match redirect.host_str() {
+        Some("localhost" | "127.0.0.1") => IpAddr::V4(Ipv4Addr::LOCALHOST),
+        Some("[::1]") => IpAddr::V6(Ipv6Addr::LOCALHOST),
+        _ => return Err(invalid("the host must be localhost, 127.0.0.1 or [::1]")),
+    };
+    let port = redirect.port_or_known_default().unwrap_or(80);
+    let listener = TcpListener::bind((ip, port)).await.map_err(SystemError::IoError)?;
+    if port == 0 {
+        let port = listener.local_addr().map_err(SystemError::IoError)?.port();
+        redirect.set_port(Some(port)).map_err(|_| invalid("cannot set the port"))?;
+    }
+
+    let state = random_token();
+    let code_verifier = random_token();
+    let code_challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()));
+    let scopes = scopes.iter().map(Scope::as_str).collect::<Vec<_>>().join(" ");
+    let authorize_url = Url::parse_with_params(&twitch.endpoints.oauth_authorize, [
+        ("client_id", twitch.client_id.as_str()),
+        ("redirect_uri", redirect.as_str()),
+        ("response_type", "code"),
+        ("scope", &scopes),
+        ("state", &state),
+        ("code_challenge", &code_challenge),
+        ("code_challenge_method", "S256"),
+    ]).map_err(|e| TwitchError::TwitchError(format!("Invalid authorize endpoint: {}", e)))?;
+    #[cfg(feature = "tracing")]
+    tracing::debug!(port = redirect.port_or_known_default(), "authorization-code listener started");
+    Ok(AuthCodeRequest {
+        authorize_url: authorize_url.to_string(),
+        redirect_uri: redirect.to_string(),
+        listener,
+        path: redirect.path().to_string(),
+        state,
+        code_verifier,
+    })
+}
+
+/// Waits for the browser to be redirected back, then exchanges the code for tokens.
+pub async fn wait_for_auth_code (twitch: &TwitchClient, request: AuthCodeRequest) -> Result<(TokenSet, String, String), AuthError> {
+    let code = receive_code(&request).await?;
+    let payload = [
+        ("client_id", twitch.client_id.as_str()),
+        ("code", &code),
+        ("code_verifier", &request.code_verifier),
+        ("grant_type", "authorization_code"),
+        ("redirect_uri", &request.redirect_uri),
+    ];
+    // An authorization code may only be used once, so the request is never re-sent.
+    let request = HttpRequest::new("oauth2/token", Method::POST, &twitch.endpoints.oauth_token).with_form(&payload).retryable(false);
+    let response = send(twitch, request).await?;
+    let body = response.json().unwrap_or_default();
+    if !response.is_success() {
+        return Err(AuthError::OAuth { status: response.status, code: oauth_error_code(&body).unwrap_or_default() });
+    }
+    let tokens = token_set(&body).ok_or_else(|| TwitchError::MissingField("access_token".into()))?;
+    let session = validate(twitch, &tokens.access_token).await?;
+    Ok((tokens, session.user_id, session.login))
+}
+
+/// Serves the redirect listener until the browser arrives with a code or an error.
+///
+/// Gives up after [`LOGIN_TIMEOUT`], with [`AuthError::StateMismatch`] if only redirects
+/// carrying another state arrived.
+async fn receive_code (request: &AuthCodeRequest) -> Result<String, AuthError> {
+    let deadline = tokio::time::sleep(LOGIN_TIMEOUT);
+    tokio::pin!(deadline);
+    // Request heads are read concurrently: browsers open idle speculative connections,
+    // which must not hold up the redirect.
+    let mut reading = JoinSet::new();
+    let mut mismatched = false;
+    loop {
+        tokio::select! {
+            _ = &mut deadline => {
+                return Err(if mismatched { AuthError::StateMismatch } else { AuthError::RedirectTimeout });
+            },
+            accepted = request.listener.accept() => {
+                let (mut socket, _) = accepted.map_err(SystemError::IoError)?;
+                reading.spawn(async move {
+                    let target = timeout(READ_TIMEOUT, read_target(&mut socket)).await;
+                    (socket, target.ok().and_then(Result::ok).flatten())
+                });
+            },
+            Some(read) = reading.join_next() => {
+                // Browsers also ask for `/favicon.ico`.
+                let Ok((mut socket, Some(target))) = read else {
+                    continue;
+                };
+                match answer(request, &mut socket, &target).await {
+                    None => {},
+                    // A stale tab or a forged link: only the redirect of this login ends it.
+                    Some(Err(AuthError::StateMismatch)) => mismatched = true,
+                    Some(result) => return result,
+                }
+            },
+        }
+    }
+}
+
+/// Answers one request to the listener, returning `None` if it is not the redirect.
+async fn answer (request: &AuthCodeRequest, socket: &mut TcpStream, target: &str) -> Option<Result<String, AuthError>> {
+    let Ok(url) = Url::parse(&format!("http://localhost{}", target)) else {
+        let _ = respond(socket, "400 Bad Request", FAILED_PAGE).await;
+        return None;
+    };
+    if url.path() != request.path {
+        let _ = respond(socket, "404 Not Found", FAILED_PAGE).await;
+        return None;
+    }
+    let param = |name: &str| url.query_pairs().find(|(key, _)| key == name).map(|(_, value)| value.into_owned());
+    if param("state").as_deref() != Some(request.state.as_str()) {
+        let _ = respond(socket, "400 Bad Request", FAILED_PAGE).await;
+        return Some(Err(AuthError::StateMismatch));
+    }
+    if let Some(code) = param("code") {
+        let _ = respond(socket, "200 OK", DONE_PAGE).await;
+        return Some(Ok(code));
+    }
+    let _ = respond(socket, "200 OK", FAILED_PAGE).await;
+    Some(match param("error").as_deref() {
+        Some("access_denied") => Err(AuthError::AccessDenied),
+        // Errors on the redirect are the ones `oauth2/token` answers with 400 Bad Request.
+        error => Err(AuthError::OAuth { status: 400, code: error.unwrap_or_default().to_string() }),
+    })
+}
+
+/// Reads a request head and returns its target, e.g. `/callback?code=...`.
+async fn read_target (socket: &mut TcpStream) -> std::io::Result<Option<String>> {
+    let mut raw = Vec::new();
+    let mut buf = [0u8; 4096];
+    while !raw.windows(4).any(|w| w == b"\r\n\r\n") {
+        let n = socket.read(&mut buf).await?;
+        if n == 0 || raw.len() > MAX_REQUEST_HEAD {
+            return Ok(None);
+        }
+        raw.extend_from_slice(&buf[..n]);
+    }
+    let head = String::from_utf8_lossy(&raw);
+    let mut request_line = head.lines().next().unwrap_or_default().split_whitespace();
+    match (request_line.next(), request_line.next()) {
+        (Some("GET"), Some(target)) => Ok(Some(target.to_string())),
+        _ => Ok(None),
+    }
+}
+
+async fn respond (socket: &mut TcpStream, status: &str, page: &str) -> std::io::Result<()> {
+    let response = format!(
+        "HTTP/1.1 {}\r\ncontent-type: text/html; charset=utf-8\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+        status, page.len(), page
+    );
+    socket.write_all(response.as_bytes()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use super::*;
+    use crate::{client_type::ClientType, mock::MockTwitch};
+
+    #[tokio::test]
+    async fn authorization_code_flow_captures_the_redirect() -> Result<(), Box<dyn Error>> {
+        let mock = MockTwitch::start().await?;
+        let mut client = TwitchClient::new(&ClientType::web(), &None).await?.with_endpoints(mock.endpoints());
+        let request = client.request_auth_code("http://127.0.0.1:0/callback", &[Scope::UserReadFollows]).await?;
+        assert!(request.authorize_url.contains("code_challenge_method=S256"));
+        let browser = tokio::spawn(reqwest::get(request.authorize_url.clone()));
+        client.auth_code(request).await?;
+        assert!(browser.await??.text().await?.contains("You are logged in"));
+        assert_eq!((client.user_id.as_deref(), client.login.as_deref()), (Some("12345678"), Some("mock_viewer")));
+        assert_eq!(client.access_token.as_deref(), Some("mock-access-token"));
+        assert_eq!(client.scopes, [Scope::UserReadFollows]);
+
+        mock.state().authorize_error = Some("access_denied".into());
+        let request = client.request_auth_code("http://localhost:0/", &[]).await?;
+        let browser = tokio::spawn(reqwest::get(request.authorize_url.clone()));
+        assert!(matches!(client.auth_code(request).await, Err(AuthError::AccessDenied)));
+        browser.await??;
+        assert!(matches!(client.request_auth_code("https://example.com/callback", &[]).await, Err(AuthError::InvalidRedirectUri(_))));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn idle_connections_and_forged_redirects_do_not_end_the_login() -> Result<(), Box<dyn Error>> {
+        let mock = MockTwitch::start().await?;
+        let mut client = TwitchClient::new(&ClientType::web(), &None).await?.with_endpoints(mock.endpoints());
+        let request = client.request_auth_code("http://127.0.0.1:0/callback", &[]).await?;
+        // A speculative connection that never sends a request.
+        let _idle = TcpStream::connect(request.listener.local_addr()?).await?;
+        let forged = format!("{}?state=forged&code=stolen", request.redirect_uri);
+        let authorize_url = request.authorize_url.clone();
+        let browser = tokio::spawn(async move {
+            let forged = reqwest::get(forged).await?.status();
+            let redirect = reqwest::get(authorize_url).await?.text().await?;
+            Ok::<_, reqwest::Error>((forged, redirect))
+        });
+        tokio::time::timeout(Duration::from_secs(5), client.auth_code(request)).await??;
+        let (forged, redirect) = browser.await??;
+        assert_eq!(forged, 400);
+        assert!(redirect.contains("You are logged in"));
+        assert_eq!(client.access_token.as_deref(), Some("mock-access-token"));
+        Ok(())
+    }
+}
@@ -0,0 +1,95 @@
+use std::marker::PhantomData;
+
+use serde_json::Value;
+
+use crate::{TwitchClient, error::TwitchError, gql::{GQLOperation, decode_response, execute_batch}, operations::GqlOperation};
+
+/// Several GraphQL operations sent to Twitch as one JSON array.
+///
+/// Every [`GqlBatch::add`] returns a [`BatchSlot`] that is later used to take the typed result
+/// out of the [`BatchResponse`]. Each slot carries its own success or error.
+/// Batches larger than Twitch's limit of 35 operations are split into several requests.
+///
+/// ```rust,no_run
+/// use twitch_gql_rs::{TwitchClient, operations::{StreamInfoQuery, StreamInfoVariables}};
+///
+/// # async fn example(client: &TwitchClient) -> Result<(), Box<dyn std::error::Error>> {
+/// let mut batch = client.batch();
+/// let first = batch.add::<StreamInfoQuery>(StreamInfoVariables { channel: "first".into() })?;
+/// let second = batch.add::<StreamInfoQuery>(StreamInfoVariables { channel: "second".into() })?;
+/// let mut response = batch.send().await?;
+/// let first = response.take(first)?;
+/// if let Ok(second) = response.take(second) {
+///     println!("{} {}", first.login, second.login);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct GqlBatch<'a> {
+    client: &'a TwitchClient,
+    operations: Vec<GQLOperation>,
+}
+
+/// Handle to the result of one operation of a [`GqlBatch`].
+#[must_use = "a slot is the only way to read the operation's result"]
+pub struct BatchSlot<Op: GqlOperation> {
+    index: usize,
+    operation: PhantomData<Op>,
+}
+
+/// Responses of a sent [`GqlBatch`].
+#[derive(Debug)]
+pub struct BatchResponse {
+    responses: Vec<Option<Value>>,
+}
+
+impl<'a> GqlBatch<'a> {
+    pub(crate) fn new(client: &'a TwitchClient) -> Self {
+        GqlBatch { client, operations: Vec::new() }
+    }
+
+    /// Queues an operation and returns the slot its result will be available in.
+    pub fn add<Op: GqlOperation>(&mut self, variables: Op::Variables) -> Result<BatchSlot<Op>, TwitchError> {
+        self.client.require_scopes(Op::REQUIRED_SCOPES)?;
+        self.operations.push(GQLOperation::for_operation::<Op>(variables, &self.client.persisted_queries)?);
+        Ok(BatchSlot { index: self.operations.len() - 1, operation: PhantomData })
+    }
+
+    /// Number of queued operations.
+    pub fn len(&self) -> usize {
+        self.operations.len()
+    }
+
+    /// Returns `true` if no operation has been queued.
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+
+    /// Sends every queued operation.
+    ///
+    /// An error here means the whole request failed. Errors of single operations are returned by [`BatchResponse::take`].
+    pub async fn send(self) -> Result<BatchResponse, TwitchError> {
+        let responses = execute_batch(self.client, &self.operations).await?;
+        Ok(BatchResponse { responses: responses.into_iter().map(Some).collect() })
+    }
+}
+
+impl BatchResponse {
+    /// Takes the typed result of one operation out of the response.
+    pub fn take<Op: GqlOperation>(&mut self, slot: BatchSlot<Op>) -> Result<Op::Response, TwitchError> {
+        let response = self.responses.get_mut(slot.index)
+            .and_then(Option::take)
+            .ok_or_else(|| TwitchError::TwitchError(format!("No response for batch slot {}", slot.index)))?;
+        decode_response::<Op>(response)
+    }
+
+    /// Number of operation responses.
+    pub fn len(&self) -> usize {
+        self.responses.len()
+    }
+
+    /// Returns `true` if the batch had no operations.
+    pub fn is_empty(&self) -> bool {
+        self.responses.is_empty()
+    }
+}
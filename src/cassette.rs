@@ -0,0 +1,343 @@
+use std::{collections::HashSet, path::{Path, PathBuf}, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tokio::{fs, sync::Mutex};
+
+use crate::{error::{SystemError, TwitchError}, http::{HttpRequest, HttpResponse, RequestBody}};
+
+/// Placeholder written in place of every scrubbed value.
+pub const REDACTED: &str = "[redacted]";
+
+/// Keys scrubbed at any depth from the requests and responses of the OAuth endpoints, which carry little else.
+const OAUTH_SECRET_KEYS: &[&str] = &[
+    "access_token", "refresh_token", "device_code", "user_code", "code", "code_verifier", "client_secret", "token",
+    // Carries the user code of the device flow in its query string.
+    "verification_uri",
+];
+
+/// Query parameters of usher playlist requests, signed with a playback token.
+const USHER_SECRET_PARAMS: &[&str] = &["sig", "token"];
+
+/// Secrets in the responses of other operations, by operation name and full JSON path.
+const SECRET_RESPONSE_PATHS: &[(&str, &[&str])] = &[
+    ("integrity", &["token"]),
+    ("PlaybackAccessToken", &["data", "streamPlaybackAccessToken", "value"]),
+    ("PlaybackAccessToken", &["data", "streamPlaybackAccessToken", "signature"]),
+    ("PlaybackAccessToken", &["data", "videoPlaybackAccessToken", "value"]),
+    ("PlaybackAccessToken", &["data", "videoPlaybackAccessToken", "signature"]),
+];
+
+/// A single recorded request and the response Twitch sent back.
+///
+/// Headers are never recorded, so `Authorization` and `Client-Integrity` values stay out of cassettes.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct Interaction {
+    /// Same as [`HttpRequest::operation`].
+    pub operation: String,
+    pub method: String,
+    pub url: String,
+    #[serde(default)]
+    pub query: Vec<(String, String)>,
+    /// JSON body, form fields as an object, or `null` for requests without a body.
+    #[serde(default)]
+    pub request: Value,
+    pub status: u16,
+    pub response: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default)]
+struct CassetteFile {
+    interactions: Vec<Interaction>,
+}
+
+#[derive(Debug)]
+enum Mode {
+    Record(PathBuf),
+    Replay,
+}
+
+#[derive(Debug)]
+struct State {
+    mode: Mode,
+    interactions: Vec<Interaction>,
+    used: Vec<bool>,
+}
+
+/// Records every HTTP exchange of a [`TwitchClient`](crate::TwitchClient) to a file, or replays
+/// a recorded file without touching the network.
+///
+/// OAuth tokens and codes, integrity tokens, playback tokens and usher signatures are replaced by
+/// [`REDACTED`] before they are written. Only those exchanges are scrubbed, at the places Twitch puts
+/// the secrets, so other responses are replayed exactly as recorded. Requests are scrubbed the same
+/// way before they are matched against a recording.
+///
+/// ```rust,no_run
+/// use std::path::Path;
+/// use twitch_gql_rs::{TwitchClient, cassette::Cassette};
+///
+/// # async fn example(client: TwitchClient) -> Result<(), Box<dyn std::error::Error>> {
+/// // Record once against Twitch...
+/// let recording = client.clone().with_cassette(Cassette::record(Path::new("inventory.json")));
+/// recording.get_inventory().await?;
+///
+/// // ...then replay offline, e.g. in tests.
+/// let replaying = client.with_cassette(Cassette::replay(Path::new("inventory.json")).await?);
+/// let inventory = replaying.get_inventory().await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Cassette {
+    state: Arc<Mutex<State>>,
+    redacted: Arc<HashSet<String>>,
+}
+
+impl Cassette {
+    fn with_mode(mode: Mode, interactions: Vec<Interaction>) -> Self {
+        Cassette {
+            state: Arc::new(Mutex::new(State { mode, used: vec![false; interactions.len()], interactions })),
+            redacted: Arc::new(HashSet::new()),
+        }
+    }
+
+    /// Sends requests to Twitch and writes every exchange to `path`, which is overwritten.
+    pub fn record(path: &Path) -> Self {
+        Cassette::with_mode(Mode::Record(path.to_path_buf()), Vec::new())
+    }
+
+    /// Serves responses from a cassette previously written by [`Cassette::record`].
+    pub async fn replay(path: &Path) -> Result<Self, SystemError> {
+        if !path.exists() {
+            return Err(SystemError::FileNotFound);
+        }
+        let file = fs::read_to_string(path).await?;
+        let file: CassetteFile = serde_json::from_str(&file).map_err(SystemError::DeserializationProblem)?;
+        Ok(Cassette::from_interactions(file.interactions))
+    }
+
+    /// Serves responses from in-memory interactions.
+    pub fn from_interactions(interactions: Vec<Interaction>) -> Self {
+        Cassette::with_mode(Mode::Replay, interactions)
+    }
+
+    /// Also scrubs `key` from the JSON bodies, form fields and query parameters of every operation.
+    ///
+    /// Responses that use `key` for ordinary data are then replayed with [`REDACTED`] in its place.
+    pub fn with_redacted_key(mut self, key: &str) -> Self {
+        Arc::make_mut(&mut self.redacted).insert(key.to_string());
+        self
+    }
+
+    /// Interactions recorded or loaded so far.
+    pub async fn interactions(&self) -> Vec<Interaction> {
+        self.state.lock().await.interactions.clone()
+    }
+
+    /// Returns the recorded response to `request` in replay mode, or `None` in record mode.
+    ///
+    /// Each recorded response is served once. An exact match on the operation, method, query and
+    /// body is preferred; otherwise the next unused response of the same operation is served.
+    pub(crate) async fn replay_response(&self, request: &HttpRequest) -> Option<Result<HttpResponse, TwitchError>> {
+        let mut state = self.state.lock().await;
+        if let Mode::Record(_) = state.mode {
+            return None;
+        }
+        let wanted = self.interaction(request, 0, String::new());
+        let unused = |state: &State, exact: bool| state.interactions.iter().enumerate().position(|(i, recorded)| {
+            !state.used[i] && recorded.operation == wanted.operation && recorded.method == wanted.method
+                && (!exact || (recorded.query == wanted.query && recorded.request == wanted.request))
+        });
+        let Some(index) = unused(&state, true).or_else(|| unused(&state, false)) else {
+            return Some(Err(TwitchError::Cassette(format!("no recorded response left for {} {}", request.method, request.operation))));
+        };
+        state.used[index] = true;
+        let recorded = &state.interactions[index];
+        Some(Ok(HttpResponse { status: recorded.status, body: recorded.response.clone() }))
+    }
+
+    /// Appends a scrubbed exchange to the cassette file in record mode.
+    pub(crate) async fn record_exchange(&self, request: &HttpRequest, response: &HttpResponse) -> Result<(), TwitchError> {
+        let mut state = self.state.lock().await;
+        let interaction = self.interaction(request, response.status, self.scrub_response(&request.operation, &response.body));
+        let Mode::Record(path) = &state.mode else {
+            return Ok(());
+        };
+        let path = path.clone();
+        state.interactions.push(interaction);
+        state.used.push(true);
+        let file = serde_json::to_string_pretty(&CassetteFile { interactions: state.interactions.clone() })?;
+        // The lock is held while writing so that concurrent requests cannot reorder the file.
+        fs::write(&path, file).await.map_err(|e| TwitchError::Cassette(format!("failed to write {}: {}", path.display(), e)))
+    }
+
+    fn interaction(&self, request: &HttpRequest, status: u16, response: String) -> Interaction {
+        let body = match &request.body {
+            RequestBody::Empty => Value::Null,
+            RequestBody::Json(body) => body.clone(),
+            RequestBody::Form(form) => Value::Object(form.iter().map(|(k, v)| (k.clone(), Value::String(v.clone()))).collect::<Map<_, _>>()),
+        };
+        let operation = &request.operation;
+        Interaction {
+            operation: operation.clone(),
+            method: request.method.to_string(),
+            url: request.url.clone(),
+            query: request.query.iter()
+                .map(|(k, v)| (k.clone(), if self.is_redacted(operation, k) { REDACTED.to_string() } else { v.clone() }))
+                .collect(),
+            request: self.scrub(operation, body),
+            status,
+            response,
+        }
+    }
+
+    /// Whether `key` is scrubbed at any depth of the exchanges of `operation`.
+    fn is_redacted(&self, operation: &str, key: &str) -> bool {
+        self.redacted.contains(key)
+            || (operation.starts_with("oauth2/") && OAUTH_SECRET_KEYS.contains(&key))
+            || (operation.starts_with("usher/") && USHER_SECRET_PARAMS.contains(&key))
+    }
+
+    fn scrub(&self, operation: &str, value: Value) -> Value {
+        match value {
+            Value::Object(map) => Value::Object(map.into_iter()
+                .map(|(k, v)| {
+                    let v = if self.is_redacted(operation, &k) && !v.is_null() { Value::String(REDACTED.to_string()) } else { self.scrub(operation, v) };
+                    (k, v)
+                })
+                .collect()),
+            Value::Array(items) => Value::Array(items.into_iter().map(|v| self.scrub(operation, v)).collect()),
+            other => other,
+        }
+    }
+
+    /// Scrubs JSON response bodies. Other bodies, e.g. HLS playlists, are kept as they are
+    /// and should be reviewed before a cassette is shared.
+    fn scrub_response(&self, operation: &str, body: &str) -> String {
+        let Ok(json) = serde_json::from_str::<Value>(body) else {
+            return body.to_string();
+        };
+        // A batch is named after its operations and answered with one response per operation.
+        let names: Vec<&str> = operation.split(',').collect();
+        let json = match self.scrub(operation, json) {
+            Value::Array(items) if items.len() == names.len() => Value::Array(items.into_iter().zip(names)
+                .map(|(item, name)| redact_paths(name, item))
+                .collect()),
+            json => redact_paths(operation, json),
+        };
+        json.to_string()
+    }
+}
+
+/// Replaces the secrets found at the [`SECRET_RESPONSE_PATHS`] of `operation`.
+fn redact_paths(operation: &str, mut response: Value) -> Value {
+    for (_, path) in SECRET_RESPONSE_PATHS.iter().filter(|(name, _)| *name == operation) {
+        if let Some(secret) = response.pointer_mut(&format!("/{}", path.join("/"))) && !secret.is_null() {
+            *secret = Value::String(REDACTED.to_string());
+        }
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use reqwest::Method;
+    use serde_json::json;
+
+    use super::*;
+    use crate::{TwitchClient, client_type::ClientType, endpoints::Endpoints, error::SlugError, http::send, mock::MockTwitch, operations::{GqlOperation, PlaybackAccessTokenQuery, PlaybackAccessTokenVariables, StreamInfoQuery, StreamInfoVariables}};
+
+    #[tokio::test]
+    async fn cassette_records_scrubbed_exchanges_and_replays_them() -> Result<(), Box<dyn Error>> {
+        let path = std::env::temp_dir().join(format!("twitch-gql-rs-cassette-{}.json", uuid::Uuid::new_v4()));
+        let mock = MockTwitch::start().await?;
+        mock.state().slugs.insert("Marvel Rivals".into(), "marvel-rivals".into());
+        mock.state().claim_outcomes.insert("drop".into(), "ELIGIBLE_FOR_ALL".into());
+        let client = TwitchClient::new(&ClientType::web(), &None).await?;
+        let recording = client.clone().with_endpoints(mock.endpoints()).with_cassette(Cassette::record(&path));
+        assert_eq!(recording.claim_drop("drop").await?.status, "ELIGIBLE_FOR_ALL");
+        assert_eq!(recording.get_slug("Marvel Rivals").await?, "marvel-rivals");
+
+        let recorded = fs::read_to_string(&path).await?;
+        assert!(!recorded.contains("mock-integrity-token") && recorded.contains(REDACTED));
+
+        // Nothing listens on the discard port: every response must come from the cassette.
+        let replaying = client.with_endpoints(Endpoints::from_base_url("http://127.0.0.1:9")).with_cassette(Cassette::replay(&path).await?);
+        fs::remove_file(&path).await?;
+        assert_eq!(replaying.get_slug("Marvel Rivals").await?, "marvel-rivals");
+        assert_eq!(replaying.claim_drop("drop").await?.status, "ELIGIBLE_FOR_ALL");
+        assert!(matches!(replaying.get_slug("Marvel Rivals").await, Err(SlugError::TwitchError(TwitchError::Cassette(_)))));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn recorded_cassettes_hold_no_secrets() -> Result<(), Box<dyn Error>> {
+        let path = std::env::temp_dir().join(format!("twitch-gql-rs-cassette-{}.json", uuid::Uuid::new_v4()));
+        let mock = MockTwitch::start().await?;
+        let mut client = TwitchClient::new(&ClientType::web(), &None).await?.with_endpoints(mock.endpoints()).with_cassette(Cassette::record(&path));
+
+        // OAuth: the device flow, a refresh, the authorization-code flow and a revocation.
+        let device_auth = client.request_device_auth().await?;
+        client.auth(device_auth).await?;
+        client.refresh_access_token().await?;
+        let request = client.request_auth_code("http://127.0.0.1:0/callback", &[]).await?;
+        let browser = tokio::spawn(reqwest::get(request.authorize_url.clone()));
+        client.auth_code(request).await?;
+        browser.await??;
+
+        // GQL and usher: a playback token, alone and in a batch, then the playlist request signed with it.
+        let playback = client.get_playback_access_token("channel").await?;
+        let mut batch = client.batch();
+        let _stream = batch.add::<StreamInfoQuery>(StreamInfoVariables { channel: "channel".into() })?;
+        let _playback = batch.add::<PlaybackAccessTokenQuery>(PlaybackAccessTokenVariables::live("channel"))?;
+        batch.send().await?;
+        let url = format!("{}/api/channel/hls/channel.m3u8", client.endpoints.usher);
+        send(&client, HttpRequest::new("usher/hls", Method::GET, &url).with_query(&[("sig", &playback.signature), ("token", &playback.value)])).await?;
+        client.logout().await?;
+
+        let file: CassetteFile = serde_json::from_str(&fs::read_to_string(&path).await?)?;
+        fs::remove_file(&path).await?;
+        let mut recorded = Vec::new();
+        for interaction in &file.interactions {
+            let response = serde_json::from_str(&interaction.response).unwrap_or_default();
+            let query = Value::Object(interaction.query.iter().map(|(k, v)| (k.clone(), Value::String(v.clone()))).collect());
+            recorded.extend([Value::String(interaction.url.clone()), interaction.request.clone(), response, query]);
+        }
+        let recorded = Value::Array(recorded).to_string();
+        let playback_value = serde_json::to_string(&playback.value)?;
+        let issued = ["mock-access-token", "mock-refresh-token", "mock-device-code", "MOCKCODE", "mock-auth-code", "mock-signature", "mock-integrity-token", playback_value.trim_matches('"')];
+        for secret in issued {
+            assert!(!recorded.contains(secret), "`{secret}` leaked");
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn graphql_fields_named_like_secrets_are_replayed_intact() -> Result<(), Box<dyn Error>> {
+        struct RedeemCodeQuery;
+        impl GqlOperation for RedeemCodeQuery {
+            const NAME: &'static str = "RedeemCode";
+            const QUERY: Option<&'static str> = Some("query RedeemCode($code: String!) { redeemCode(code: $code) { code value token } }");
+            const RESPONSE_PATH: &'static [&'static str] = &["data", "redeemCode"];
+            type Variables = Value;
+            type Response = Value;
+        }
+
+        let path = std::env::temp_dir().join(format!("twitch-gql-rs-cassette-{}.json", uuid::Uuid::new_v4()));
+        let redeemed = json!({ "code": "SUMMER-2026", "value": "500 bits", "token": "gift" });
+        let recording = Cassette::record(&path);
+        let request = HttpRequest::new(RedeemCodeQuery::NAME, Method::POST, "http://127.0.0.1:9/gql")
+            .with_json(json!({ "operationName": RedeemCodeQuery::NAME, "variables": { "code": "SUMMER-2026" } }));
+        let response = HttpResponse { status: 200, body: json!({ "data": { "redeemCode": redeemed } }).to_string() };
+        recording.record_exchange(&request, &response).await?;
+
+        let client = TwitchClient::new(&ClientType::web(), &None).await?
+            .with_endpoints(Endpoints::from_base_url("http://127.0.0.1:9"))
+            .with_cassette(Cassette::replay(&path).await?);
+        fs::remove_file(&path).await?;
+        assert_eq!(client.execute::<RedeemCodeQuery>(json!({ "code": "SUMMER-2026" })).await?, redeemed);
+        Ok(())
+    }
+}
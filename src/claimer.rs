@@ -0,0 +1,188 @@
+use std::{collections::HashSet, sync::{Arc, Mutex}, time::Duration};
+
+use tokio::time::sleep;
+
+use crate::{TwitchClient, error::{ClaimDropError, TwitchError}};
+
+/// Result of a claim attempt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClaimOutcome {
+    /// The reward was claimed.
+    Claimed,
+    /// The reward had already been claimed, e.g. on another device. Counts as success.
+    AlreadyClaimed,
+    /// Twitch refused the claim or the request failed.
+    Failed(String),
+}
+
+/// A claim attempt, reported to the handlers registered with [`Claimer::on_event`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClaimEvent {
+    pub drop_instance_id: String,
+    pub drop_id: String,
+    pub drop_name: String,
+    pub campaign_id: String,
+    pub campaign_name: String,
+    pub outcome: ClaimOutcome,
+}
+
+type EventHandler = dyn Fn(&ClaimEvent) + Send + Sync;
+
+/// Claims the rewards of completed drops in a [`TwitchClient`]'s inventory.
+///
+/// A drop is claimed once it has a drop instance ID, enough minutes watched and is not claimed yet.
+/// The claimer remembers every drop instance it has attempted, so each one is claimed at most once
+/// per claimer, and clones share that memory. Only attempts that failed to reach Twitch are retried
+/// on the next pass.
+///
+/// ```rust,no_run
+/// use std::time::Duration;
+/// use twitch_gql_rs::{TwitchClient, claimer::Claimer};
+///
+/// # async fn example(client: TwitchClient) -> Result<(), Box<dyn std::error::Error>> {
+/// let claimer = Claimer::new(client).on_event(|event| println!("{}: {:?}", event.drop_name, event.outcome));
+///
+/// // Once, on demand...
+/// claimer.claim_completed().await?;
+/// // ...or every 5 minutes until Ctrl-C.
+/// claimer.run(Duration::from_secs(5 * 60), async { let _ = tokio::signal::ctrl_c().await; }).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct Claimer {
+    client: TwitchClient,
+    attempted: Arc<Mutex<HashSet<String>>>,
+    handlers: Vec<Arc<EventHandler>>,
+}
+
+impl std::fmt::Debug for Claimer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Claimer").field("attempted", &self.attempted.lock().unwrap().len()).field("handlers", &self.handlers.len()).finish_non_exhaustive()
+    }
+}
+
+impl Claimer {
+    pub fn new(client: TwitchClient) -> Self {
+        Claimer { client, attempted: Arc::default(), handlers: Vec::new() }
+    }
+
+    /// Calls `handler` with every [`ClaimEvent`].
+    pub fn on_event(mut self, handler: impl Fn(&ClaimEvent) + Send + Sync + 'static) -> Self {
+        self.handlers.push(Arc::new(handler));
+        self
+    }
+
+    /// Claims every completed drop in the inventory that has not been attempted yet, and returns
+    /// the attempts made.
+    pub async fn claim_completed(&self) -> Result<Vec<ClaimEvent>, TwitchError> {
+        let inventory = self.client.get_inventory().await?.inventory.dropCampaignsInProgress.unwrap_or_default();
+        let mut events = Vec::new();
+        for campaign in &inventory {
+            for drop in &campaign.timeBasedDrops {
+                let progress = &drop.self_drop;
+                let Some(drop_instance_id) = &progress.dropInstanceID else {
+                    continue;
+                };
+                if progress.isClaimed || progress.currentMinutesWatched < drop.requiredMinutesWatched {
+                    continue;
+                }
+                // Checked and recorded together, so concurrent passes of cloned claimers cannot both claim it.
+                if !self.attempted.lock().unwrap().insert(drop_instance_id.clone()) {
+                    continue;
+                }
+                let outcome = match self.client.claim_drop(drop_instance_id).await {
+                    Ok(_) => ClaimOutcome::Claimed,
+                    Err(ClaimDropError::DropAlreadyClaimed) => ClaimOutcome::AlreadyClaimed,
+                    Err(ClaimDropError::FailedClaimDrops(message)) => ClaimOutcome::Failed(message),
+                    Err(ClaimDropError::TwitchError(e)) => {
+                        self.attempted.lock().unwrap().remove(drop_instance_id);
+                        ClaimOutcome::Failed(e.to_string())
+                    },
+                };
+                let event = ClaimEvent {
+                    drop_instance_id: drop_instance_id.clone(),
+                    drop_id: drop.id.clone(),
+                    drop_name: drop.name.clone(),
+                    campaign_id: campaign.id.clone(),
+                    campaign_name: campaign.name.clone(),
+                    outcome,
+                };
+                for handler in &self.handlers {
+                    handler(&event);
+                }
+                events.push(event);
+            }
+        }
+        Ok(events)
+    }
+
+    /// Calls [`Claimer::claim_completed`] right away and then every `interval`, until `cancel` completes.
+    pub async fn run(&self, interval: Duration, cancel: impl Future<Output = ()>) -> Result<(), TwitchError> {
+        tokio::select! {
+            result = self.claim_every(interval) => result,
+            _ = cancel => Ok(()),
+        }
+    }
+
+    async fn claim_every(&self, interval: Duration) -> Result<(), TwitchError> {
+        loop {
+            self.claim_completed().await?;
+            sleep(interval).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use super::*;
+    use crate::{client_type::ClientType, mock::MockTwitch, structs::*};
+
+    #[tokio::test]
+    async fn claimer_claims_completed_drops_once() -> Result<(), Box<dyn Error>> {
+        let mock = MockTwitch::start().await?;
+        let drop = |id: &str, minutes: u64| InventoryTimeBasedDrops {
+            id: id.into(),
+            name: format!("{id} reward"),
+            requiredMinutesWatched: 60,
+            self_drop: InventorySelf {
+                currentMinutesWatched: minutes,
+                dropInstanceID: (minutes == 60).then(|| format!("12345678#campaign#{id}")),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        {
+            let mut state = mock.state();
+            state.inventory.push(DropCampaignsInProgress {
+                id: "campaign".into(),
+                name: "Rivals Drops".into(),
+                timeBasedDrops: vec![drop("done", 60), drop("elsewhere", 60), drop("pending", 30)],
+                ..Default::default()
+            });
+            state.claim_outcomes.insert("12345678#campaign#elsewhere".into(), "DROP_INSTANCE_ALREADY_CLAIMED".into());
+        }
+        let client = mock.authorized_client(&ClientType::web()).await?;
+
+        let reported = Arc::new(Mutex::new(Vec::new()));
+        let claimer = Claimer::new(client).on_event({
+            let reported = reported.clone();
+            move |event| reported.lock().unwrap().push(event.drop_id.clone())
+        });
+        let events = claimer.claim_completed().await?;
+        assert_eq!(events.iter().map(|e| (e.drop_id.as_str(), e.outcome.clone())).collect::<Vec<_>>(), [
+            ("done", ClaimOutcome::Claimed),
+            ("elsewhere", ClaimOutcome::AlreadyClaimed),
+        ]);
+        assert_eq!(events[0].drop_instance_id, "12345678#campaign#done");
+        assert_eq!(*reported.lock().unwrap(), ["done", "elsewhere"]);
+
+        claimer.clone().run(Duration::from_millis(5), sleep(Duration::from_millis(50))).await?;
+        assert!(claimer.claim_completed().await?.is_empty());
+        let claims = mock.requests().iter().filter(|r| r.operations().iter().any(|o| o == "DropsPage_ClaimDropRewards")).count();
+        assert_eq!(claims, 2);
+        Ok(())
+    }
+}
@@ -28,8 +28,7 @@ impl ClientType {
     /// Ready-made version for web client (browser).
     pub fn web () -> ClientType {
         let user_agent = ["Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/138.0.0.0 Safari/537.36"];
-        let client_type = ClientType::new().with_url("https://www.twitch.tv").with_id("kimne78kx3ncx6brgo4mv6wki5h1ko").with_user_agent(&user_agent);
-        client_type
+        ClientType::new().with_url("https://www.twitch.tv").with_id("kimne78kx3ncx6brgo4mv6wki5h1ko").with_user_agent(&user_agent)
     }
 
     /// Ready-made configuration for the mobile version of the web (m.twitch.tv).
@@ -43,8 +42,7 @@ impl ClientType {
             "Mozilla/5.0 (Linux; Android 16; LM-Q720) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/138.0.7204.158 Mobile Safari/537.36",
             "Mozilla/5.0 (Linux; Android 16; LM-X420) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/138.0.7204.158 Mobile Safari/537.36",
         ];
-        let client_type = ClientType::new().with_url("https://m.twitch.tv").with_id("r8s4dac0uhzifbpu9sjdiwzctle17ff").with_user_agent(&user_agents);
-        client_type
+        ClientType::new().with_url("https://m.twitch.tv").with_id("r8s4dac0uhzifbpu9sjdiwzctle17ff").with_user_agent(&user_agents)
     }
 
     /// Configuration for the Twitch Android app (emulates app headers).
@@ -58,14 +56,12 @@ impl ClientType {
             "Dalvik/2.1.0 (Linux; U; Android 15; SM-A566E Build/AP3A.240905.015.A2) tv.twitch.android.app/25.3.0/2503006",
             "Dalvik/2.1.0 (Linux; U; Android 14; SM-X306B Build/UP1A.231005.007) tv.twitch.android.app/25.3.0/2503006",
         ];
-        let client_type = ClientType::new().with_url("https://www.twitch.tv").with_id("kd1unb4b3q4t58fwlpcbzcbnm76a8fp").with_user_agent(&user_agents);
-        client_type
+        ClientType::new().with_url("https://www.twitch.tv").with_id("kd1unb4b3q4t58fwlpcbzcbnm76a8fp").with_user_agent(&user_agents)
     }
 
     /// Configuration for Smart TV / Smart Box Twitch client.
     pub fn smartbox () -> ClientType {
         let user_agent = ["Mozilla/5.0 (Linux; Android 7.1; Smart Box C1) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/138.0.0.0 Safari/537.36"];
-        let client_type = ClientType::new().with_url("https://android.tv.twitch.tv").with_id("ue6666qo983tsx6so1t0vnawi233wa").with_user_agent(&user_agent);
-        client_type
+        ClientType::new().with_url("https://android.tv.twitch.tv").with_id("ue6666qo983tsx6so1t0vnawi233wa").with_user_agent(&user_agent)
     }
 }
\ No newline at end of file
@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+
+/// Base URLs used by a [`TwitchClient`](crate::TwitchClient) for every request it sends.
+///
+/// The defaults point at Twitch. Override them to run the client against a local stand-in
+/// (integration tests, staging, a recording proxy...).
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(default)]
+pub struct Endpoints {
+    /// GraphQL endpoint, `https://gql.twitch.tv/gql` by default.
+    pub gql: String,
+    /// Endpoint issuing `Client-Integrity` tokens.
+    pub integrity: String,
+    /// OAuth device authorization endpoint.
+    pub oauth_device: String,
+    /// OAuth authorize page the browser is sent to in the authorization-code flow.
+    pub oauth_authorize: String,
+    /// OAuth token endpoint.
+    pub oauth_token: String,
+    /// OAuth token validation endpoint.
+    pub oauth_validate: String,
+    /// OAuth token revocation endpoint.
+    pub oauth_revoke: String,
+    /// Base URL of the HLS playlist service (usher).
+    pub usher: String,
+    /// Spade (analytics) endpoint. When `None`, it is discovered from the channel page.
+    pub spade: Option<String>,
+}
+
+impl Default for Endpoints {
+    fn default() -> Self {
+        Endpoints {
+            gql: "https://gql.twitch.tv/gql".to_string(),
+            integrity: "https://gql.twitch.tv/integrity".to_string(),
+            oauth_device: "https://id.twitch.tv/oauth2/device".to_string(),
+            oauth_authorize: "https://id.twitch.tv/oauth2/authorize".to_string(),
+            oauth_token: "https://id.twitch.tv/oauth2/token".to_string(),
+            oauth_validate: "https://id.twitch.tv/oauth2/validate".to_string(),
+            oauth_revoke: "https://id.twitch.tv/oauth2/revoke".to_string(),
+            usher: "https://usher.ttvnw.net".to_string(),
+            spade: None,
+        }
+    }
+}
+
+impl Endpoints {
+    /// Points every endpoint at a single server, keeping Twitch's paths.
+    ///
+    /// ```rust
+    /// use twitch_gql_rs::endpoints::Endpoints;
+    ///
+    /// let endpoints = Endpoints::from_base_url("http://127.0.0.1:8080");
+    /// assert_eq!(endpoints.gql, "http://127.0.0.1:8080/gql");
+    /// assert_eq!(endpoints.oauth_token, "http://127.0.0.1:8080/oauth2/token");
+    /// ```
+    pub fn from_base_url(base_url: &str) -> Self {
+        let base = base_url.trim_end_matches('/');
+        Endpoints {
+            gql: format!("{base}/gql"),
+            integrity: format!("{base}/integrity"),
+            oauth_device: format!("{base}/oauth2/device"),
+            oauth_authorize: format!("{base}/oauth2/authorize"),
+            oauth_token: format!("{base}/oauth2/token"),
+            oauth_validate: format!("{base}/oauth2/validate"),
+            oauth_revoke: format!("{base}/oauth2/revoke"),
+            usher: base.to_string(),
+            spade: Some(format!("{base}/spade")),
+        }
+    }
+}
@@ -1,4 +1,8 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use thiserror::Error;
+
+use crate::scope::Scope;
 #[derive(Error, Debug)]
 pub enum SystemError {
     #[error("The file already exists")]
@@ -11,10 +15,28 @@ pub enum SystemError {
     DeserializationProblem(serde_json::Error),
     #[error("File not found")]
     FileNotFound,
+    #[error("No session has been saved to the store")]
+    SessionNotFound,
+    #[error("No account named `{0}`")]
+    UnknownAccount(String),
+    #[error("Failed to decrypt the session: {0}")]
+    SessionDecryption(String),
+    #[error("The session was saved in format version {found}, this release reads up to version {supported}")]
+    UnsupportedSessionVersion {
+        found: u64,
+        supported: u64,
+    },
     #[error("Failed to retrieve the required headers: {0}")]
-    HeadersError(#[from] Box<dyn std::error::Error>),
+    HeadersError(#[from] Box<dyn std::error::Error + Send + Sync>),
+    #[error("Invalid header '{name}': {details}")]
+    InvalidHeader {
+        name: String,
+        details: String,
+    },
     #[error("Error creating client: {0}")]
     ClientBuilderError(#[from] reqwest::Error),
+    #[error("The {0} option cannot be combined with a custom HTTP client")]
+    CustomClientConflict(String),
     #[error("Invalid proxy URL: '{proxy_url}'. {details}")]
     InvalidProxy {
         proxy_url: String,
@@ -27,7 +49,37 @@ pub enum AuthError {
     #[error("{0}")]
     TwitchError(#[from] TwitchError),
     #[error("The device authorization code has expired")]
-    DeviceTokenExpired
+    DeviceTokenExpired,
+    #[error("The user denied the device authorization request")]
+    AccessDenied,
+    #[error("The device code is invalid or has already been used")]
+    InvalidDeviceCode,
+    #[error("The authorization was cancelled")]
+    Cancelled,
+    #[error("Invalid redirect URI {0}")]
+    InvalidRedirectUri(String),
+    #[error("The authorization redirect carried an unexpected state")]
+    StateMismatch,
+    #[error("The browser was not redirected back before the login timed out")]
+    RedirectTimeout,
+    #[error("OAuth error {status}: {code}")]
+    OAuth {
+        status: u16,
+        code: String,
+    },
+    #[error("The client has no access token")]
+    NotAuthenticated,
+    #[error("The cookies do not contain `{0}`")]
+    MissingCookie(String),
+    #[error("The access token was issued to client ID {actual}, not {expected}")]
+    ClientIdMismatch {
+        expected: String,
+        actual: String,
+    },
+    #[error("The access token has expired or been revoked")]
+    InvalidToken,
+    #[error("{0}")]
+    SystemError(#[from] SystemError),
 }
 
 impl From<reqwest::Error> for AuthError {
@@ -54,6 +106,39 @@ pub enum TwitchError {
     ReqwestProblem(#[from] reqwest::Error),
     #[error("Twitch error: {0}")]
     TwitchError(String),
+    #[error("Failed to refresh the access token: {0}")]
+    TokenRefreshFailed(String),
+    #[error("The session lacks the required scopes: {}", .0.iter().map(|s| s.as_str()).collect::<Vec<_>>().join(", "))]
+    MissingScopes(Vec<Scope>),
+    #[error("Cassette error: {0}")]
+    Cassette(String),
+    #[error("GraphQL error: {}", .errors.iter().map(|e| e.message.as_str()).collect::<Vec<_>>().join("; "))]
+    GraphQL {
+        errors: Vec<GqlError>,
+        /// Partial `data` returned alongside the errors, if any.
+        data: Option<Value>,
+    },
+}
+
+impl TwitchError {
+    /// Returns `true` if this is a GraphQL error with the given message (case-insensitive).
+    pub fn has_graphql_error(&self, message: &str) -> bool {
+        match self {
+            TwitchError::GraphQL { errors, .. } => errors.iter().any(|e| e.message.eq_ignore_ascii_case(message)),
+            _ => false,
+        }
+    }
+}
+
+/// A single entry of the GraphQL `errors` array returned by Twitch.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct GqlError {
+    pub message: String,
+    /// Path of the field that failed, made of field names and list indices.
+    #[serde(default)]
+    pub path: Option<Vec<Value>>,
+    #[serde(default)]
+    pub extensions: Option<Value>,
 }
 
 #[derive(Debug, Error)]
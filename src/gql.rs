@@ -2,15 +2,16 @@ use std::io::Write;
 
 use base64::{Engine, engine::general_purpose};
 use flate2::{Compression, write::GzEncoder};
-use reqwest::{Client, Response};
+use reqwest::Method;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 
-use crate::{error::*, structs::*};
+use crate::{TwitchClient, error::*, http::{HttpRequest, HttpResponse, send}, operations::*, structs::*};
 
-pub const GQL_URL: &'static str = "https://gql.twitch.tv/gql";
+/// Largest number of operations Twitch accepts in a single batched request.
+pub const MAX_BATCH_SIZE: usize = 35;
 
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, Clone)]
 #[allow(non_snake_case)]
 pub struct GQLOperation {
     operationName: String,
@@ -20,13 +21,21 @@ pub struct GQLOperation {
     query: Option<Value>,
     #[serde(skip_serializing_if = "Option::is_none")]
     variables: Option<Value>,
+    /// Query document sent instead of the persisted hash if Twitch does not know the hash.
+    #[serde(skip)]
+    fallback_query: Option<String>,
+    #[serde(skip)]
+    mutation: bool,
+    /// Whether the request must carry a `Client-Integrity` header.
+    #[serde(skip)]
+    integrity: bool,
 }
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, Clone)]
 #[allow(non_snake_case)]
 struct Extensions {
     persistedQuery: PersistedQuery
 }
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, Clone)]
 #[allow(non_snake_case)]
 struct PersistedQuery {
     version: u16,
@@ -35,14 +44,16 @@ struct PersistedQuery {
 
 impl GQLOperation {
     fn new (name: &str) -> Self {
-        let operation = GQLOperation {
+        GQLOperation {
             operationName: name.to_string(),
             extensions: None,
             query: None,
             variables: None,
-        };
-        operation
-    } 
+            fallback_query: None,
+            mutation: false,
+            integrity: false,
+        }
+    }
 
     fn with_extensions (mut self, sha256: &str) -> Self {
         let extensions = Extensions { persistedQuery: PersistedQuery { version: 1, sha256Hash: sha256.to_string() } };
@@ -50,33 +61,64 @@ impl GQLOperation {
         self
     }
 
+    /// Builds `Op`, preferring hashes and query documents registered in `persisted_queries` over the built-in ones.
+    pub fn for_operation <Op: GqlOperation>(variables: Op::Variables, persisted_queries: &PersistedQueries) -> Result<Self, TwitchError> {
+        let mut operation = GQLOperation::new(Op::NAME);
+        let hash = persisted_queries.hash(Op::NAME).or_else(|| Op::HASH.map(str::to_string));
+        let query = persisted_queries.query(Op::NAME).or_else(|| Op::QUERY.map(str::to_string));
+        match (hash, query) {
+            (Some(hash), query) => {
+                operation = operation.with_extensions(&hash);
+                operation.fallback_query = query;
+            },
+            (None, Some(query)) => operation = operation.with_query(query),
+            (None, None) => {},
+        }
+        operation.variables = Some(serde_json::to_value(variables)?);
+        operation.mutation = Op::MUTATION;
+        operation.integrity = Op::REQUIRES_INTEGRITY;
+        Ok(operation)
+    }
+
+    /// The same operation with a `Client-Integrity` header attached.
+    fn with_integrity (&self) -> Self {
+        let mut operation = self.clone();
+        operation.integrity = true;
+        operation
+    }
+
+    /// The same operation sent as a full query document, if one is known.
+    fn without_persisted_query (&self) -> Option<Self> {
+        let query = self.fallback_query.as_ref()?;
+        Some(GQLOperation {
+            operationName: self.operationName.clone(),
+            extensions: None,
+            query: Some(Value::String(query.clone())),
+            variables: self.variables.clone(),
+            fallback_query: None,
+            mutation: self.mutation,
+            integrity: self.integrity,
+        })
+    }
+
     fn with_query <V: Serialize>(mut self, query: V) -> Self {
         self.query = Some(serde_json::to_value(query).expect("serialize query"));
         self
     }
 
-    fn with_variables <V: Serialize>(mut self, vars: V) -> Self {
-        self.variables = Some(serde_json::to_value(vars).expect("serialize variables"));
-        self
-    }
 }
 
-async fn check_response_error (response: &Response) -> Result<(), TwitchError> {
-    if !response.status().is_success() {
-        return Err(TwitchError::HttpError(response.status().as_u16()));
+fn check_response_error (response: &HttpResponse) -> Result<(), TwitchError> {
+    if !response.is_success() {
+        return Err(TwitchError::HttpError(response.status));
     }
     Ok(())
 }
 
-fn get_value (v: Value, name: &str) -> Result<Value, TwitchError> {
-    let get = v.get(&name).ok_or_else(|| TwitchError::MissingField(name.into()))?;
-    Ok(get.clone())
-}
-
 fn get_value_from_vec (v: Value, name_vec: &[&str]) -> Result<Value, TwitchError> {
     let mut current = &v;
     for name in name_vec {
-        current = current.get(&name).ok_or_else(|| TwitchError::MissingField(name.to_string()))?;
+        current = current.get(name).ok_or_else(|| TwitchError::MissingField(name.to_string()))?;
     }
     Ok(current.clone())
 }
@@ -88,7 +130,143 @@ fn gzip_compress_then_base64(data: &[u8]) -> String {
     general_purpose::STANDARD.encode(compressed)
 }
 
-pub async fn send_watch_gql (client: &Client, user_id: &str, channel_login: &str, channel_id: &str, broadcast_id: &str, game_name: Option<&str>, game_id: Option<&str>) -> Result<(), TwitchError> {
+const PERSISTED_QUERY_NOT_FOUND: &str = "PersistedQueryNotFound";
+const FAILED_INTEGRITY_CHECK: &str = "failed integrity check";
+
+fn has_error_message (response: &Value, message: &str) -> bool {
+    response.get("errors").and_then(|e| e.as_array()).is_some_and(|errors| {
+        errors.iter().any(|e| e.get("message").and_then(|m| m.as_str()).is_some_and(|m| m.eq_ignore_ascii_case(message)))
+    })
+}
+
+/// Turns a non-empty GraphQL `errors` array into [`TwitchError::GraphQL`], keeping partial `data`.
+fn check_graphql_errors (response: &Value) -> Result<(), TwitchError> {
+    let Some(errors) = response.get("errors").and_then(|e| e.as_array()) else {
+        return Ok(());
+    };
+    if errors.is_empty() {
+        return Ok(());
+    }
+    let errors = errors.iter().map(|e| match serde_json::from_value::<GqlError>(e.clone()) {
+        Ok(error) => error,
+        Err(_) => GqlError { message: e.to_string(), path: None, extensions: None },
+    }).collect();
+    let data = response.get("data").filter(|d| !d.is_null()).cloned();
+    Err(TwitchError::GraphQL { errors, data })
+}
+
+/// Deserializes the value found at `Op::RESPONSE_PATH` of a single operation response.
+pub fn decode_response <Op: GqlOperation>(response: Value) -> Result<Op::Response, TwitchError> {
+    check_graphql_errors(&response)?;
+    let value = get_value_from_vec(response, Op::RESPONSE_PATH)?;
+    let response: Op::Response = serde_json::from_value(value)?;
+    Ok(response)
+}
+
+/// Posts one operation, or a batch of them as a JSON array.
+/// Mutations are only retried if the retry policy opts them in.
+async fn post_gql (twitch: &TwitchClient, operations: &[GQLOperation], batch: bool) -> Result<Value, TwitchError> {
+    let retryable = operations.iter().all(|op| twitch.retry_policy.allows(&op.operationName, op.mutation));
+    let body = match operations {
+        [operation] if !batch => serde_json::to_value(operation)?,
+        _ => serde_json::to_value(operations)?,
+    };
+    let name = operations.iter().map(|op| op.operationName.as_str()).collect::<Vec<_>>().join(",");
+    let mut request = HttpRequest::new(&name, Method::POST, &twitch.endpoints.gql).with_json(body).retryable(retryable);
+    if operations.iter().any(|op| op.integrity) {
+        let integrity = twitch.integrity.get(twitch).await?;
+        request = request.with_header("Client-Integrity", &integrity.token);
+    }
+    twitch.token_state.refresh_if_expiring(twitch).await?;
+    let sent_with = twitch.tokens();
+    let mut gql = send(twitch, request.clone()).await?;
+    if gql.status == 401 && let Some(tokens) = sent_with.filter(|t| t.refresh_token.is_some()) {
+        twitch.token_state.refresh(twitch, Some(&tokens.access_token)).await?;
+        gql = send(twitch, request).await?;
+    }
+    check_response_error(&gql)?;
+    gql.json()
+}
+
+/// Sends one operation. It is sent again once with the full query document on `PersistedQueryNotFound`,
+/// and once with a fresh integrity token on `failed integrity check`.
+async fn post_operation (twitch: &TwitchClient, operation: &GQLOperation) -> Result<Value, TwitchError> {
+    let mut operation = operation.clone();
+    let mut response = post_gql(twitch, std::slice::from_ref(&operation), false).await?;
+    if has_error_message(&response, PERSISTED_QUERY_NOT_FOUND) && let Some(fallback) = operation.without_persisted_query() {
+        operation = fallback;
+        response = post_gql(twitch, std::slice::from_ref(&operation), false).await?;
+    }
+    if has_error_message(&response, FAILED_INTEGRITY_CHECK) {
+        twitch.integrity.invalidate().await;
+        response = post_gql(twitch, &[operation.with_integrity()], false).await?;
+    }
+    Ok(response)
+}
+
+/// Sends `Op` and returns the whole response.
+async fn send_operation <Op: GqlOperation>(twitch: &TwitchClient, variables: Op::Variables) -> Result<Value, TwitchError> {
+    twitch.require_scopes(Op::REQUIRED_SCOPES)?;
+    let gql = GQLOperation::for_operation::<Op>(variables, &twitch.persisted_queries)?;
+    post_operation(twitch, &gql).await
+}
+
+/// Sends `Op` and deserializes the value found at `Op::RESPONSE_PATH`.
+pub async fn execute <Op: GqlOperation>(twitch: &TwitchClient, variables: Op::Variables) -> Result<Op::Response, TwitchError> {
+    let gql = send_operation::<Op>(twitch, variables).await?;
+    decode_response::<Op>(gql)
+}
+
+/// Sends operations as JSON arrays of at most [`MAX_BATCH_SIZE`] operations each.
+/// Returns one response per operation, in the same order.
+pub async fn execute_batch (twitch: &TwitchClient, operations: &[GQLOperation]) -> Result<Vec<Value>, TwitchError> {
+    send_batch(twitch, operations, true).await
+}
+
+async fn send_batch (twitch: &TwitchClient, operations: &[GQLOperation], resend: bool) -> Result<Vec<Value>, TwitchError> {
+    let mut responses = Vec::with_capacity(operations.len());
+    for chunk in operations.chunks(MAX_BATCH_SIZE) {
+        let gql = post_gql(twitch, chunk, true).await?;
+        let mut items = match gql {
+            Value::Array(items) => items,
+            other => return Err(TwitchError::TwitchError(format!("Expected a batch response array, got: {}", other))),
+        };
+        items.resize(chunk.len(), Value::Null);
+        responses.extend(items);
+    }
+
+    if !resend {
+        return Ok(responses);
+    }
+
+    // Operations are sent again, like in `post_operation`, on unknown hashes and failed integrity checks.
+    let mut indices = Vec::new();
+    let mut resent = Vec::new();
+    let mut integrity_failed = false;
+    for (i, operation) in operations.iter().enumerate() {
+        if has_error_message(&responses[i], PERSISTED_QUERY_NOT_FOUND) && let Some(fallback) = operation.without_persisted_query() {
+            indices.push(i);
+            resent.push(fallback);
+        } else if has_error_message(&responses[i], FAILED_INTEGRITY_CHECK) {
+            integrity_failed = true;
+            indices.push(i);
+            resent.push(operation.with_integrity());
+        }
+    }
+    if integrity_failed {
+        twitch.integrity.invalidate().await;
+    }
+    if !resent.is_empty() {
+        let retried = Box::pin(send_batch(twitch, &resent, false)).await?;
+        for (i, response) in indices.into_iter().zip(retried) {
+            responses[i] = response;
+        }
+    }
+    Ok(responses)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn send_watch_gql (twitch: &TwitchClient, user_id: &str, channel_login: &str, channel_id: &str, broadcast_id: &str, game_name: Option<&str>, game_id: Option<&str>) -> Result<(), TwitchError> {
     let user_id: u64 = user_id.parse().map_err(|_| TwitchError::TwitchError("Invalid user_id".into()))?;
     let event = json!({
         "event": "minute-watched",
@@ -113,218 +291,110 @@ pub async fn send_watch_gql (client: &Client, user_id: &str, channel_login: &str
     let json_str = serde_json::to_string(&payload_array)?;
     let compressed_b64 = gzip_compress_then_base64(json_str.as_bytes());
 
-    let variables = json!({
-        "input": {
-            "data": compressed_b64,
-            "repository": "twilight",
-            "encoding": "GZIP_B64",
-        }
-    });
-
-    let query = r#"
-        mutation SendEvents($input: SendSpadeEventsInput!) {
-            sendSpadeEvents(input: $input) {
-                statusCode
-            }
+    let variables = SendEventsVariables {
+        input: SendSpadeEventsInput {
+            data: compressed_b64,
+            repository: "twilight".to_string(),
+            encoding: "GZIP_B64".to_string(),
         }
-    "#;
-
-    let gql = GQLOperation::new("SendEvents").with_variables(variables).with_query(query);
-
-    let gql = client.post(GQL_URL).json(&gql).send().await?;
-    check_response_error(&gql).await?;
+    };
 
-    let json_resp: Value = gql.json().await?;
+    let status_code = match execute::<SendEventsMutation>(twitch, variables).await {
+        Ok(status_code) => status_code,
+        Err(TwitchError::MissingField(_)) => return Err(TwitchError::MissingField("statusCode".into())),
+        Err(e) => return Err(e),
+    };
+    if status_code == 204 {
+        Ok(())
+    } else {
+        Err(TwitchError::TwitchError(format!("Unexpected statusCode: {}", status_code)))
+    }
+}
 
-    match get_value_from_vec(json_resp, &["data", "sendSpadeEvents", "statusCode"]) {
-        Ok(status_value) => {
-            if let Some(204) = status_value.as_u64() {
-                Ok(())
-            } else {
-                return Err(TwitchError::TwitchError(format!("Unexpected statusCode: {}", status_value)));
-            }
-        },
-        Err(_) => return Err(TwitchError::MissingField("statusCode".into()))
+pub async fn stream_info (twitch: &TwitchClient, channel_login: &str) -> Result<StreamInfo, StreamInfoError> {
+    let variables = StreamInfoVariables { channel: channel_login.to_string() };
+    match execute::<StreamInfoQuery>(twitch, variables).await {
+        Ok(stream_info) => Ok(stream_info),
+        Err(TwitchError::JsonError(_)) => Err(StreamInfoError::ChannelNotFound),
+        Err(e) => Err(e.into()),
     }
 }
 
-pub async fn stream_info (client: &Client, channel_login: &str) -> Result<StreamInfo, StreamInfoError> {
-    let gql = GQLOperation::new("VideoPlayerStreamInfoOverlayChannel").with_extensions("198492e0857f6aedead9665c81c5a06d67b25b58034649687124083ff288597d").with_variables(json!({
-        "channel": channel_login
-    }));
-    let gql = client.post(GQL_URL).json(&gql).send().await?;
-    check_response_error(&gql).await?;
-    let gql: Value = gql.json().await?;
-    let gql = get_value_from_vec(gql, &["data", "user"])?;
-    let stream_info: StreamInfo = match serde_json::from_value(gql) {
-        Ok(v) => v,
-        Err(_) => return Err(StreamInfoError::ChannelNotFound)
+pub async fn claim_drop (twitch: &TwitchClient, drop_instance_id: &str) -> Result<ClaimDrop, ClaimDropError> {
+    let variables = ClaimDropRewardsVariables { input: ClaimDropRewardsInput { dropInstanceID: drop_instance_id.to_string() } };
+    let gql = send_operation::<ClaimDropRewardsMutation>(twitch, variables).await?;
+    let claim_drop = match decode_response::<ClaimDropRewardsMutation>(gql.clone()) {
+        Ok(claim_drop) => claim_drop,
+        Err(TwitchError::MissingField(_)) => return Err(ClaimDropError::FailedClaimDrops("Missing claimDropRewards field".into())),
+        Err(e) => return Err(e.into()),
     };
-    Ok(stream_info)
-}
-pub async fn claim_drop (client: &Client, drop_instance_id: &str) -> Result<ClaimDrop, ClaimDropError> {
-    let gql = GQLOperation::new("DropsPage_ClaimDropRewards").with_extensions("a455deea71bdc9015b78eb49f4acfbce8baa7ccbedd28e549bb025bd0f751930").with_variables(json!({
-        "input": {
-            "dropInstanceID": drop_instance_id
-        }
-    }));
-    let gql = client.post(GQL_URL).json(&gql).send().await?;
-    check_response_error(&gql).await?;
-    let gql: Value = gql.json().await?;
-    if let Ok(claim_drop) = get_value_from_vec(gql.clone(), &["data", "claimDropRewards"]) {
-        let claim_drop: ClaimDrop = serde_json::from_value(claim_drop)?;
-        if claim_drop.status == "ELIGIBLE_FOR_ALL" {
-            return Ok(claim_drop)
-        } else if claim_drop.status == "DROP_INSTANCE_ALREADY_CLAIMED" {
-            return Err(ClaimDropError::DropAlreadyClaimed);
-        } else {
-            if let Ok(error) = get_value_from_vec(gql, &["data", "error"]) {
-                return Err(ClaimDropError::FailedClaimDrops(error.to_string()));
-            } else {
-                return Err(ClaimDropError::FailedClaimDrops("Missing error field".into()));
-            }
-        }
+    if claim_drop.status == "ELIGIBLE_FOR_ALL" {
+        Ok(claim_drop)
+    } else if claim_drop.status == "DROP_INSTANCE_ALREADY_CLAIMED" {
+        Err(ClaimDropError::DropAlreadyClaimed)
+    } else if let Ok(error) = get_value_from_vec(gql, &["data", "error"]) {
+        Err(ClaimDropError::FailedClaimDrops(error.to_string()))
     } else {
-        return Err(ClaimDropError::FailedClaimDrops("Missing claimDropRewards field".into()));
+        Err(ClaimDropError::FailedClaimDrops("Missing error field".into()))
     }
-    
 }
 
-pub async fn inventory (client: &Client) -> Result<GetInventory, TwitchError> {
-    let gql = GQLOperation::new("Inventory").with_extensions("d86775d0ef16a63a33ad52e80eaff963b2d5b72fada7c991504a57496e1d8e4b").with_variables(json!({
-        "fetchRewardCampaigns": false
-    }));
-    let gql = client.post(GQL_URL).json(&gql).send().await?;
-    check_response_error(&gql).await?;
-    let gql: Value = gql.json().await?;
-    let gql = get_value_from_vec(gql, &["data", "currentUser"])?;
-    let inventory: GetInventory = serde_json::from_value(gql)?;
-    Ok(inventory)
+pub async fn inventory (twitch: &TwitchClient) -> Result<GetInventory, TwitchError> {
+    execute::<InventoryQuery>(twitch, InventoryVariables { fetchRewardCampaigns: false }).await
 }
 
-pub async fn current_drop (client: &Client, channel_login: &str) -> Result<CurrentDrop, TwitchError> {
-    let gql = GQLOperation::new("DropCurrentSessionContext").with_extensions("4d06b702d25d652afb9ef835d2a550031f1cf762b193523a92166f40ea3d142b").with_variables(json!({
-        "channelLogin": channel_login,
-    }));
-    let gql = client.post(GQL_URL).json(&gql).send().await?;
-    check_response_error(&gql).await?;
-    let gql: Value = gql.json().await?;
-    let gql = get_value_from_vec(gql, &["data", "currentUser", "dropCurrentSession"])?;
-    let current: CurrentDrop = serde_json::from_value(gql)?;
-    Ok(current)
+pub async fn current_drop (twitch: &TwitchClient, channel_login: &str) -> Result<CurrentDrop, TwitchError> {
+    let variables = DropCurrentSessionContextVariables { channelLogin: channel_login.to_string() };
+    let response = send_operation::<DropCurrentSessionContextQuery>(twitch, variables).await?;
+    check_graphql_errors(&response)?;
+    // Twitch answers `null` while the channel has no drop session, which is not a malformed response.
+    if response.pointer("/data/currentUser/dropCurrentSession").is_some_and(Value::is_null) {
+        return Err(TwitchError::MissingField("dropCurrentSession".into()));
+    }
+    decode_response::<DropCurrentSessionContextQuery>(response)
 }
 
-pub async fn campaign (client: &Client) -> Result<Drops, TwitchError> {
-    let gql = GQLOperation::new("ViewerDropsDashboard").with_extensions("5a4da2ab3d5b47c9f9ce864e727b2cb346af1e3ea8b897fe8f704a97ff017619").with_variables(json!({
-        "fetchRewardCampaigns": false
-    }));
-    let gql = client.post(GQL_URL).json(&gql).send().await?;
-    check_response_error(&gql).await?;
-    let gql: Value = gql.json().await?;
-    let gql = get_value_from_vec(gql, &["data", "currentUser"])?;
-    let drops: Drops = serde_json::from_value(gql)?;
-    Ok(drops)
+pub async fn campaign (twitch: &TwitchClient) -> Result<Drops, TwitchError> {
+    execute::<ViewerDropsDashboardQuery>(twitch, ViewerDropsDashboardVariables { fetchRewardCampaigns: false }).await
 }
 
-pub async fn campaign_details (client: &Client, user_login: &str, drop_id: &str) -> Result<CampaignDetails, CampaignDetailsError> {
-    let gql = GQLOperation::new("DropCampaignDetails").with_extensions("039277bf98f3130929262cc7c6efd9c141ca3749cb6dca442fc8ead9a53f77c1").with_variables(json!({
-        "channelLogin": user_login,
-        "dropID": drop_id,
-    }));
-    let gql = client.post(GQL_URL).json(&gql).send().await?;
-    check_response_error(&gql).await?;
-    let gql: Value = gql.json().await?;
-    let details = get_value_from_vec(gql, &["data", "user", "dropCampaign"])?;
-    let details: CampaignDetails = match serde_json::from_value(details) {
-        Ok(v) => v,
-        Err(_) => return Err(CampaignDetailsError::CampaignNotFound)
-    };
-    Ok(details)
+pub async fn campaign_details (twitch: &TwitchClient, user_login: &str, drop_id: &str) -> Result<CampaignDetails, CampaignDetailsError> {
+    let variables = DropCampaignDetailsVariables { channelLogin: user_login.to_string(), dropID: drop_id.to_string() };
+    match execute::<DropCampaignDetailsQuery>(twitch, variables).await {
+        Ok(details) => Ok(details),
+        Err(TwitchError::JsonError(_)) => Err(CampaignDetailsError::CampaignNotFound),
+        Err(e) => Err(e.into()),
+    }
 }
 
-pub async fn available_drops (client: &Client, channel_id: &str) -> Result<AvailableDrops, AvailableDropsError> {
-    let gql = GQLOperation::new("DropsHighlightService_AvailableDrops").with_extensions("782dad0f032942260171d2d80a654f88bdd0c5a9dddc392e9bc92218a0f42d20").with_variables(json!({
-        "channelID": channel_id
-    }));
-    let gql = client.post(GQL_URL).json(&gql).send().await?;
-    check_response_error(&gql).await?;
-    let gql: Value = gql.json().await?;
-    let channel = match get_value_from_vec(gql, &["data", "channel"]) {
-        Ok(value) => value,
-        Err(_) => return Err(AvailableDropsError::ChannelNotFound)
-    };
-    let available_drops: AvailableDrops = serde_json::from_value(channel)?;
-    Ok(available_drops)
+pub async fn available_drops (twitch: &TwitchClient, channel_id: &str) -> Result<AvailableDrops, AvailableDropsError> {
+    let variables = AvailableDropsVariables { channelID: channel_id.to_string() };
+    match execute::<AvailableDropsQuery>(twitch, variables).await {
+        Ok(available_drops) => Ok(available_drops),
+        Err(TwitchError::MissingField(_)) => Err(AvailableDropsError::ChannelNotFound),
+        Err(e) => Err(e.into()),
+    }
 }
 
-pub async fn playback_access_token (client: &Client, channel_login: &str) -> Result<PlaybackAccessToken, TwitchError> {
-    let gql = GQLOperation::new("PlaybackAccessToken").with_extensions("ed230aa1e33e07eebb8928504583da78a5173989fadfb1ac94be06a04f3cdbe9").with_variables(json!({
-        "isLive": true,
-        "isVod": false,
-        "login": channel_login,
-        "platform": "web",
-        "playerType": "site",
-        "vodID": "",
-    }));
-    let gql = client.post(GQL_URL).json(&gql).send().await?;
-    check_response_error(&gql).await?;
-    let gql: Value = gql.json().await?;
-    let playback = get_value_from_vec(gql, &["data", "streamPlaybackAccessToken"])?;
-    let playback: PlaybackAccessToken = serde_json::from_value(playback)?;
-    Ok(playback)
+pub async fn playback_access_token (twitch: &TwitchClient, channel_login: &str) -> Result<PlaybackAccessToken, TwitchError> {
+    execute::<PlaybackAccessTokenQuery>(twitch, PlaybackAccessTokenVariables::live(channel_login)).await
 }
 
-pub async fn game_directory (client: &Client, game_slug: &str, limit: u64, drops_enabled: bool) -> Result<Vec<GameDirectory>, GameDirectoryError> {
-    let filters = if drops_enabled {
-        ["DROPS_ENABLED"]
-    } else {
-        [""]
-    };
-    let gql = GQLOperation::new("DirectoryPage_Game").with_extensions("cb5dc816e139dcb8a118f14b4b677d59abc224a4b016c4bc2bb00a47fe0ddec4").with_variables(json!({
-        "limit": limit,
-        "slug": game_slug,
-        "imageWidth": 50,
-        "includeCostreaming": false,
-        "options": {
-            "broadcasterLanguages": [],
-            "freeformTags": null,
-            "includeRestricted": ["SUB_ONLY_LIVE"],
-            "recommendationsContext": {"platform": "web"},
-            "sort": "RELEVANCE",
-            "systemFilters": filters,
-            "tags": [],
-            "requestID": "JIRA-VXP-2397",
-        },
-        "sortTypeIsRecency": false,
-    }));
-    let gql = client.post(GQL_URL).json(&gql).send().await?;
-    check_response_error(&gql).await?;
-    let gql: Value = gql.json().await?;
-    let streams = match get_value_from_vec(gql, &["data", "game", "streams"]) {
-        Ok(value) => value,
-        Err(_) => return Err(GameDirectoryError::NoStreamsFound(game_slug.into()))
+pub async fn game_directory (twitch: &TwitchClient, game_slug: &str, limit: u64, drops_enabled: bool) -> Result<Vec<GameDirectory>, GameDirectoryError> {
+    let variables = DirectoryPageGameVariables::new(game_slug, limit, drops_enabled);
+    let streams = match execute::<DirectoryPageGameQuery>(twitch, variables).await {
+        Ok(streams) => streams,
+        Err(TwitchError::MissingField(_)) => return Err(GameDirectoryError::NoStreamsFound(game_slug.into())),
+        Err(e) => return Err(e.into()),
     };
-    let edges_vec = streams.get("edges").and_then(|v| v.as_array()).ok_or_else(|| TwitchError::MissingField("edges".to_string()))?;
-    let mut directory_vec = Vec::new();
-    for edge in edges_vec {
-        let edge = edge.get("node").ok_or_else(|| TwitchError::MissingField("edge".to_string()))?;
-        let directory: GameDirectory = serde_json::from_value(edge.clone())?;
-        directory_vec.push(directory);
-    }
-    Ok(directory_vec)
+    Ok(streams.edges.into_iter().map(|edge| edge.node).collect())
 }
 
-pub async fn slug_redirect (client: &Client, game_name: &str) -> Result<String, SlugError> {
-    let gql = GQLOperation::new("DirectoryGameRedirect").with_extensions("1f0300090caceec51f33c5e20647aceff9017f740f223c3c532ba6fa59f6b6cc").with_variables(json!({
-        "name": game_name
-    }));
-    let gql = client.post(GQL_URL).json(&gql).send().await?;
-    check_response_error(&gql).await?;
-    let gql: Value = gql.json().await?;
-    let slug = get_value_from_vec(gql, &["data", "game", "slug"])?;
-    let slug = match slug.as_str() {
-        Some(s) => s,
-        None => return Err(SlugError::GameSlugParsingFailed)
-    };
-    Ok(slug.to_string())
-}
\ No newline at end of file
+pub async fn slug_redirect (twitch: &TwitchClient, game_name: &str) -> Result<String, SlugError> {
+    let variables = DirectoryGameRedirectVariables { name: game_name.to_string() };
+    match execute::<DirectoryGameRedirectQuery>(twitch, variables).await {
+        Ok(slug) => Ok(slug),
+        Err(TwitchError::JsonError(_)) => Err(SlugError::GameSlugParsingFailed),
+        Err(e) => Err(e.into()),
+    }
+}
@@ -0,0 +1,227 @@
+use reqwest::{Method, header::{HeaderName, HeaderValue}};
+use serde_json::Value;
+use tokio::time::sleep;
+
+use crate::{TwitchClient, error::TwitchError, retry::RetryPolicy};
+
+/// A single HTTP exchange with Twitch, described independently of reqwest.
+///
+/// Every GQL, OAuth, usher and spade call is built as an `HttpRequest` and passed through the
+/// client's [`Interceptor`](crate::interceptor::Interceptor)s before it is sent.
+#[derive(Debug, Clone)]
+pub struct HttpRequest {
+    /// GraphQL operation name (comma-separated for batches), or the name of the endpoint
+    /// for other calls, e.g. `oauth2/token`, `integrity`, `usher/hls`, `spade`.
+    pub operation: String,
+    pub method: Method,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub query: Vec<(String, String)>,
+    pub body: RequestBody,
+    /// Whether the request may be sent again after a transient failure.
+    pub(crate) retryable: bool,
+}
+
+/// Body of an [`HttpRequest`].
+#[derive(Debug, Clone)]
+pub enum RequestBody {
+    Empty,
+    Json(Value),
+    Form(Vec<(String, String)>),
+}
+
+/// Status and body of a response to an [`HttpRequest`].
+#[derive(Debug, Clone)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+impl HttpRequest {
+    pub fn new(operation: &str, method: Method, url: &str) -> Self {
+        HttpRequest {
+            operation: operation.to_string(),
+            method,
+            url: url.to_string(),
+            headers: Vec::new(),
+            query: Vec::new(),
+            body: RequestBody::Empty,
+            retryable: true,
+        }
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn with_query(mut self, query: &[(&str, &str)]) -> Self {
+        self.query.extend(query.iter().map(|(k, v)| (k.to_string(), v.to_string())));
+        self
+    }
+
+    pub fn with_json(mut self, body: Value) -> Self {
+        self.body = RequestBody::Json(body);
+        self
+    }
+
+    pub fn with_form(mut self, form: &[(&str, &str)]) -> Self {
+        self.body = RequestBody::Form(form.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect());
+        self
+    }
+
+    pub(crate) fn retryable(mut self, retryable: bool) -> Self {
+        self.retryable = retryable;
+        self
+    }
+
+    /// Persisted query hash of a single GraphQL operation, if this is one.
+    #[cfg(feature = "tracing")]
+    fn persisted_hash(&self) -> Option<&str> {
+        match &self.body {
+            RequestBody::Json(body) => body.pointer("/extensions/persistedQuery/sha256Hash").and_then(|h| h.as_str()),
+            _ => None,
+        }
+    }
+
+    /// GraphQL variables of a single operation, if this is one.
+    pub fn variables(&self) -> Option<&Value> {
+        match &self.body {
+            RequestBody::Json(body) => body.get("variables"),
+            _ => None,
+        }
+    }
+}
+
+impl HttpResponse {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    pub fn json(&self) -> Result<Value, TwitchError> {
+        Ok(serde_json::from_str(&self.body)?)
+    }
+}
+
+async fn send_once(twitch: &TwitchClient, request: &HttpRequest) -> Result<HttpResponse, TwitchError> {
+    let mut headers = twitch.default_headers()?;
+    for (name, value) in &request.headers {
+        let name = HeaderName::from_bytes(name.as_bytes()).map_err(|e| TwitchError::TwitchError(format!("Invalid header name: {}", e)))?;
+        let value = HeaderValue::from_str(value).map_err(|e| TwitchError::TwitchError(format!("Invalid header value: {}", e)))?;
+        headers.insert(name, value);
+    }
+    let mut builder = twitch.client.request(request.method.clone(), &request.url).headers(headers);
+    if !request.query.is_empty() {
+        builder = builder.query(&request.query);
+    }
+    builder = match &request.body {
+        RequestBody::Empty => builder,
+        RequestBody::Json(body) => builder.json(body),
+        RequestBody::Form(form) => builder.form(form),
+    };
+    let response = builder.send().await?;
+    let status = response.status().as_u16();
+    let body = response.text().await?;
+    Ok(HttpResponse { status, body })
+}
+
+/// Sends `request` over the network, or answers it from the client's cassette.
+async fn dispatch(twitch: &TwitchClient, request: &HttpRequest) -> Result<HttpResponse, TwitchError> {
+    let Some(cassette) = &twitch.cassette else {
+        return send_once(twitch, request).await;
+    };
+    if let Some(response) = cassette.replay_response(request).await {
+        return response;
+    }
+    let response = send_once(twitch, request).await?;
+    cassette.record_exchange(request, &response).await?;
+    Ok(response)
+}
+
+/// Sends `request` once the client's rate limiters allow it,
+/// retrying transient failures according to the client's [`RetryPolicy`].
+///
+/// Interceptors see every attempt: each one starts from the original request.
+#[cfg(not(feature = "tracing"))]
+pub(crate) async fn send(twitch: &TwitchClient, request: HttpRequest) -> Result<HttpResponse, TwitchError> {
+    send_attempts(twitch, request).await
+}
+
+/// Sends `request` once the client's rate limiters allow it,
+/// retrying transient failures according to the client's [`RetryPolicy`].
+///
+/// Interceptors see every attempt: each one starts from the original request.
+#[cfg(feature = "tracing")]
+pub(crate) async fn send(twitch: &TwitchClient, request: HttpRequest) -> Result<HttpResponse, TwitchError> {
+    use tracing::{Instrument, field::Empty};
+    // Headers, query strings and bodies may hold tokens, so only metadata is recorded.
+    let span = tracing::debug_span!(
+        "twitch_request",
+        operation = %request.operation,
+        method = %request.method,
+        hash = Empty,
+        attempts = Empty,
+        status = Empty,
+        latency_ms = Empty,
+        response_bytes = Empty,
+    );
+    if let Some(hash) = request.persisted_hash() {
+        span.record("hash", hash);
+    }
+    send_attempts(twitch, request).instrument(span).await
+}
+
+#[cfg(feature = "tracing")]
+fn record_attempt(result: &Result<HttpResponse, TwitchError>, attempt: u32, started: std::time::Instant) {
+    let span = tracing::Span::current();
+    span.record("attempts", attempt);
+    span.record("latency_ms", started.elapsed().as_millis() as u64);
+    match result {
+        Ok(response) => {
+            span.record("status", response.status);
+            span.record("response_bytes", response.body.len());
+        },
+        // reqwest errors include the URL, which may carry a token in its query string.
+        Err(TwitchError::ReqwestProblem(e)) => tracing::warn!(attempt, timeout = e.is_timeout(), connect = e.is_connect(), "request failed"),
+        Err(_) => tracing::warn!(attempt, "request failed"),
+    }
+}
+
+async fn send_attempts(twitch: &TwitchClient, request: HttpRequest) -> Result<HttpResponse, TwitchError> {
+    let policy = &twitch.retry_policy;
+    let mut attempt = 1;
+    loop {
+        if let Some(limiter) = &twitch.shared_rate_limiter {
+            limiter.acquire().await;
+        }
+        if let Some(limiter) = &twitch.rate_limiter {
+            limiter.acquire().await;
+        }
+        let mut attempt_request = request.clone();
+        twitch.interceptors.before_request(&mut attempt_request);
+        #[cfg(feature = "tracing")]
+        let started = std::time::Instant::now();
+        let result = match dispatch(twitch, &attempt_request).await {
+            Ok(mut response) => {
+                twitch.interceptors.after_response(&attempt_request, &mut response);
+                Ok(response)
+            },
+            Err(error) => {
+                twitch.interceptors.on_error(&attempt_request, &error);
+                Err(error)
+            },
+        };
+        #[cfg(feature = "tracing")]
+        record_attempt(&result, attempt, started);
+        let retry = request.retryable && attempt < policy.max_attempts && match &result {
+            Ok(response) => policy.is_retryable_response(response.status, &response.body),
+            Err(TwitchError::ReqwestProblem(e)) => RetryPolicy::is_transient(e),
+            Err(_) => false,
+        };
+        if !retry {
+            return result;
+        }
+        sleep(policy.backoff(attempt)).await;
+        attempt += 1;
+    }
+}
@@ -0,0 +1,62 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use reqwest::Method;
+use tokio::sync::Mutex;
+
+use crate::{TwitchClient, error::TwitchError, http::{HttpRequest, send}};
+
+/// Tokens are refreshed this long before they actually expire.
+const EXPIRY_MARGIN: TimeDelta = TimeDelta::seconds(30);
+
+/// A `Client-Integrity` token returned by Twitch's integrity endpoint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntegrityToken {
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl IntegrityToken {
+    /// Returns `true` if the token is expired or about to expire.
+    pub fn is_expired(&self) -> bool {
+        Utc::now() + EXPIRY_MARGIN >= self.expires_at
+    }
+}
+
+/// Cached integrity token, shared between clones of a client.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct IntegrityCache {
+    token: Arc<Mutex<Option<IntegrityToken>>>,
+}
+
+impl IntegrityCache {
+    /// Returns the cached token, requesting a new one if there is none or it has expired.
+    pub async fn get(&self, twitch: &TwitchClient) -> Result<IntegrityToken, TwitchError> {
+        // Holding the lock while fetching makes concurrent callers wait for a single request.
+        let mut cached = self.token.lock().await;
+        if let Some(token) = cached.as_ref().filter(|t| !t.is_expired()) {
+            return Ok(token.clone());
+        }
+        let token = fetch(twitch).await?;
+        *cached = Some(token.clone());
+        Ok(token)
+    }
+
+    /// Drops the cached token so the next [`IntegrityCache::get`] requests a new one.
+    pub async fn invalidate(&self) {
+        self.token.lock().await.take();
+    }
+}
+
+async fn fetch (twitch: &TwitchClient) -> Result<IntegrityToken, TwitchError> {
+    let request = HttpRequest::new("integrity", Method::POST, &twitch.endpoints.integrity);
+    let response = send(twitch, request).await?;
+    if !response.is_success() {
+        return Err(TwitchError::HttpError(response.status));
+    }
+    let response = response.json()?;
+    let token = response.get("token").and_then(|t| t.as_str()).ok_or_else(|| TwitchError::MissingField("token".into()))?;
+    let expiration = response.get("expiration").and_then(|e| e.as_i64()).ok_or_else(|| TwitchError::MissingField("expiration".into()))?;
+    let expires_at = DateTime::from_timestamp_millis(expiration).ok_or_else(|| TwitchError::TwitchError(format!("Invalid integrity expiration: {}", expiration)))?;
+    Ok(IntegrityToken { token: token.to_string(), expires_at })
+}
@@ -0,0 +1,69 @@
+use std::{fmt, sync::Arc};
+
+use crate::{error::TwitchError, http::{HttpRequest, HttpResponse}};
+
+/// Hooks run around every HTTP request a [`TwitchClient`](crate::TwitchClient) sends.
+///
+/// Use them to log, meter, sign or rewrite traffic. Several interceptors can be registered;
+/// `before_request` hooks run in registration order and `after_response` hooks in reverse order.
+/// Hooks run once per attempt, so a retried request is seen several times.
+///
+/// ```rust,no_run
+/// use twitch_gql_rs::{TwitchClient, http::{HttpRequest, HttpResponse}, interceptor::Interceptor};
+///
+/// struct Logger;
+///
+/// impl Interceptor for Logger {
+///     fn after_response(&self, request: &HttpRequest, response: &mut HttpResponse) {
+///         println!("{} -> {} ({} bytes)", request.operation, response.status, response.body.len());
+///     }
+/// }
+///
+/// # async fn example(client: TwitchClient) {
+/// let client = client.with_interceptor(Logger);
+/// # }
+/// ```
+pub trait Interceptor: Send + Sync {
+    /// Called before a request is sent. The request can be modified.
+    fn before_request(&self, _request: &mut HttpRequest) {}
+
+    /// Called after a response is received. The response can be modified.
+    fn after_response(&self, _request: &HttpRequest, _response: &mut HttpResponse) {}
+
+    /// Called when a request could not be sent or its response could not be read.
+    fn on_error(&self, _request: &HttpRequest, _error: &TwitchError) {}
+}
+
+/// Interceptors registered on a client, shared between its clones.
+#[derive(Clone, Default)]
+pub(crate) struct Interceptors(Vec<Arc<dyn Interceptor>>);
+
+impl fmt::Debug for Interceptors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Interceptors({})", self.0.len())
+    }
+}
+
+impl Interceptors {
+    pub fn push(&mut self, interceptor: Arc<dyn Interceptor>) {
+        self.0.push(interceptor);
+    }
+
+    pub fn before_request(&self, request: &mut HttpRequest) {
+        for interceptor in &self.0 {
+            interceptor.before_request(request);
+        }
+    }
+
+    pub fn after_response(&self, request: &HttpRequest, response: &mut HttpResponse) {
+        for interceptor in self.0.iter().rev() {
+            interceptor.after_response(request, response);
+        }
+    }
+
+    pub fn on_error(&self, request: &HttpRequest, error: &TwitchError) {
+        for interceptor in self.0.iter().rev() {
+            interceptor.on_error(request, error);
+        }
+    }
+}
@@ -0,0 +1,518 @@
+use std::{sync::Arc, time::Duration};
+
+use chrono::{DateTime, Utc};
+use tokio::time::{Instant, sleep};
+
+use crate::{TwitchClient, error::{CampaignDetailsError, GameDirectoryError, StreamInfoError, TwitchError}, retry::RetryPolicy, structs::{Channels, CurrentDrop, DropCampaignsInProgress}};
+
+/// Settings of a [`Miner`].
+#[derive(Debug, Clone)]
+pub struct MinerConfig {
+    /// Games to mine, by name and in order of preference (case-insensitive). Empty mines any game.
+    pub games: Vec<String>,
+    /// Time between two minute-watched events. Twitch credits at most one minute per minute.
+    pub watch_interval: Duration,
+    /// Time to wait before looking again when no campaign can be mined.
+    pub idle_interval: Duration,
+    /// Time after which the eligible campaigns are fetched again. They are also fetched again
+    /// whenever the miner leaves a channel.
+    pub campaigns_refresh_interval: Duration,
+    /// Number of minute-watched events Twitch may take to start a drop session on a channel
+    /// before the miner looks for another one.
+    pub max_watches_without_progress: u32,
+    /// Number of streams fetched from a game directory when a campaign allows any channel.
+    pub directory_limit: u64,
+}
+
+impl Default for MinerConfig {
+    fn default() -> Self {
+        MinerConfig {
+            games: Vec::new(),
+            watch_interval: Duration::from_secs(60),
+            idle_interval: Duration::from_secs(5 * 60),
+            campaigns_refresh_interval: Duration::from_secs(15 * 60),
+            max_watches_without_progress: 5,
+            directory_limit: 30,
+        }
+    }
+}
+
+/// Why the [`Miner`] stopped watching a channel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SwitchReason {
+    /// The stream went offline.
+    Offline,
+    /// The stream switched to another game.
+    GameChanged,
+    /// Twitch did not start a drop session within [`MinerConfig::max_watches_without_progress`] watches.
+    NoProgress,
+}
+
+/// What a [`Miner`] is doing, reported to the handlers registered with [`Miner::on_event`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MinerEvent {
+    /// A campaign was picked for mining.
+    CampaignSelected { campaign_id: String, name: String, game: String },
+    /// A live channel of the selected campaign is being watched.
+    ChannelSelected { login: String, channel_id: String, broadcast_id: String },
+    /// A minute-watched event was sent. `progress` is `None` until Twitch reports a drop session.
+    Watched { login: String, progress: Option<CurrentDrop> },
+    /// The watched channel can no longer be used for the campaign.
+    ChannelLost { login: String, reason: SwitchReason },
+    /// No campaign can be mined right now; the miner waits [`MinerConfig::idle_interval`].
+    Idle,
+    /// A request failed even after the client's retries, e.g. during a Twitch outage;
+    /// the miner waits [`MinerConfig::watch_interval`] and carries on.
+    RequestFailed { error: String },
+    /// The cancellation signal was received.
+    Stopped,
+}
+
+type EventHandler = dyn Fn(&MinerEvent) + Send + Sync;
+
+/// A campaign the miner can make progress on.
+#[derive(Debug, Clone)]
+struct Target {
+    campaign_id: String,
+    name: String,
+    game_id: String,
+    game_name: String,
+    game_slug: String,
+    /// Logins of the channels the campaign is restricted to; empty allows any channel.
+    channels: Vec<String>,
+    ends_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone)]
+struct Channel {
+    login: String,
+    channel_id: String,
+    broadcast_id: String,
+}
+
+/// Watches streams on behalf of a [`TwitchClient`] to earn time-based drops.
+///
+/// The miner repeatedly picks an eligible campaign (in-progress ones first, then those the account
+/// has not started), finds a live channel allowed by the campaign, and sends a minute-watched event
+/// every [`MinerConfig::watch_interval`]. It moves on when the stream goes offline, changes game,
+/// or the campaign has nothing left to earn. Drops that require subscriptions are skipped.
+///
+/// Failed requests are retried according to the client's [`RetryPolicy`]. Transient errors that remain
+/// are reported as [`MinerEvent::RequestFailed`] before mining resumes; other errors stop the miner.
+///
+/// ```rust,no_run
+/// use std::time::Duration;
+/// use twitch_gql_rs::{TwitchClient, miner::{Miner, MinerConfig}};
+///
+/// # async fn example(client: TwitchClient) -> Result<(), Box<dyn std::error::Error>> {
+/// let miner = Miner::new(client)
+///     .with_config(MinerConfig { games: vec!["Marvel Rivals".into()], ..Default::default() })
+///     .on_event(|event| println!("{event:?}"));
+/// miner.run(tokio::time::sleep(Duration::from_secs(4 * 60 * 60))).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct Miner {
+    client: TwitchClient,
+    config: MinerConfig,
+    handlers: Vec<Arc<EventHandler>>,
+}
+
+impl std::fmt::Debug for Miner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Miner").field("config", &self.config).field("handlers", &self.handlers.len()).finish_non_exhaustive()
+    }
+}
+
+impl Miner {
+    /// Creates a miner for an authenticated client, with the default [`MinerConfig`].
+    pub fn new(client: TwitchClient) -> Self {
+        Miner { client, config: MinerConfig::default(), handlers: Vec::new() }
+    }
+
+    pub fn with_config(mut self, config: MinerConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Calls `handler` with every [`MinerEvent`].
+    pub fn on_event(mut self, handler: impl Fn(&MinerEvent) + Send + Sync + 'static) -> Self {
+        self.handlers.push(Arc::new(handler));
+        self
+    }
+
+    /// Mines until `cancel` completes, then returns `Ok(())`.
+    ///
+    /// A request in flight when `cancel` completes is abandoned.
+    pub async fn run(&self, cancel: impl Future<Output = ()>) -> Result<(), TwitchError> {
+        let result = tokio::select! {
+            result = self.mine() => result,
+            _ = cancel => Ok(()),
+        };
+        self.emit(MinerEvent::Stopped);
+        result
+    }
+
+    async fn mine(&self) -> Result<(), TwitchError> {
+        let mut current: Option<(String, String)> = None;
+        let mut targets: Option<(Instant, Vec<Target>)> = None;
+        loop {
+            match self.mine_once(&mut current, &mut targets).await {
+                Ok(()) => {},
+                Err(e) if self.is_transient(&e) => {
+                    self.emit(MinerEvent::RequestFailed { error: e.to_string() });
+                    sleep(self.config.watch_interval).await;
+                },
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Picks a channel and watches it until the miner has to look at the campaigns again.
+    async fn mine_once(&self, current: &mut Option<(String, String)>, targets: &mut Option<(Instant, Vec<Target>)>) -> Result<(), TwitchError> {
+        let Some((target, channel)) = self.next_target(targets).await? else {
+            *current = None;
+            self.emit(MinerEvent::Idle);
+            sleep(self.config.idle_interval).await;
+            return Ok(());
+        };
+        if current.as_ref().is_none_or(|(campaign_id, _)| *campaign_id != target.campaign_id) {
+            self.emit(MinerEvent::CampaignSelected { campaign_id: target.campaign_id.clone(), name: target.name.clone(), game: target.game_name.clone() });
+        }
+        if current.as_ref().is_none_or(|(_, login)| *login != channel.login) {
+            self.emit(MinerEvent::ChannelSelected { login: channel.login.clone(), channel_id: channel.channel_id.clone(), broadcast_id: channel.broadcast_id.clone() });
+        }
+        *current = Some((target.campaign_id.clone(), channel.login.clone()));
+        let reason = self.watch(&target, channel.clone()).await?;
+        // A drop was earned or the channel is gone: the campaigns may have changed.
+        *targets = None;
+        if let Some(reason) = reason {
+            self.emit(MinerEvent::ChannelLost { login: channel.login, reason });
+            *current = None;
+        }
+        Ok(())
+    }
+
+    /// Picks the first eligible campaign with a live channel, fetching the campaigns again if
+    /// `targets` is empty or older than [`MinerConfig::campaigns_refresh_interval`].
+    async fn next_target(&self, targets: &mut Option<(Instant, Vec<Target>)>) -> Result<Option<(Target, Channel)>, TwitchError> {
+        if targets.as_ref().is_none_or(|(fetched_at, _)| fetched_at.elapsed() >= self.config.campaigns_refresh_interval) {
+            *targets = Some((Instant::now(), self.targets().await?));
+        }
+        let now = Utc::now();
+        for target in targets.iter().flat_map(|(_, targets)| targets) {
+            if target.ends_at.is_some_and(|ends_at| ends_at <= now) {
+                continue;
+            }
+            if let Some(channel) = self.find_channel(target).await? {
+                return Ok(Some((target.clone(), channel)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Whether `error` may go away by itself, judged like the client's [`RetryPolicy`] does.
+    fn is_transient(&self, error: &TwitchError) -> bool {
+        let policy = &self.client.retry_policy;
+        match error {
+            TwitchError::ReqwestProblem(e) => RetryPolicy::is_transient(e),
+            TwitchError::HttpError(status) => policy.retryable_statuses.contains(status),
+            TwitchError::GraphQL { errors, .. } => errors.iter()
+                .any(|e| policy.retryable_graphql_errors.iter().any(|message| message.eq_ignore_ascii_case(&e.message))),
+            _ => false,
+        }
+    }
+
+    /// Eligible campaigns, in-progress ones first, each group ordered by game preference and end date.
+    async fn targets(&self) -> Result<Vec<Target>, TwitchError> {
+        let now = Utc::now();
+        let inventory = self.client.get_inventory().await?.inventory.dropCampaignsInProgress.unwrap_or_default();
+        let mut in_progress: Vec<Target> = inventory.iter()
+            .filter(|campaign| has_minable_drop(campaign))
+            .map(|campaign| Target {
+                campaign_id: campaign.id.clone(),
+                name: campaign.name.clone(),
+                game_id: campaign.game.id.clone(),
+                game_name: campaign.game.name.clone(),
+                game_slug: campaign.game.slug.clone(),
+                channels: channel_logins(&campaign.allow.channels),
+                ends_at: parse_time(&campaign.endAt),
+            })
+            .filter(|target| self.game_rank(&target.game_name).is_some() && target.ends_at.is_none_or(|ends_at| ends_at > now))
+            .collect();
+
+        let mut not_started = Vec::new();
+        let campaigns = self.client.get_campaign().await?.dropCampaigns;
+        for campaign in campaigns {
+            let started = inventory.iter().any(|c| c.id == campaign.id);
+            let ended = parse_time(&campaign.endAt).is_some_and(|ends_at| ends_at <= now);
+            if started || ended || campaign.status != "ACTIVE" || !campaign.connecting.isAccountConnected || self.game_rank(&campaign.game.displayName).is_none() {
+                continue;
+            }
+            let details = match self.client.get_campaign_details(&campaign.id).await {
+                Ok(details) => details,
+                Err(CampaignDetailsError::CampaignNotFound) => continue,
+                Err(CampaignDetailsError::TwitchError(e)) => return Err(e),
+            };
+            if !details.allow.isEnabled || !details.timeBasedDrops.iter().any(|drop| drop.requiredSubs == 0 && drop.requiredMinutesWatched > 0) {
+                continue;
+            }
+            not_started.push(Target {
+                campaign_id: details.id,
+                name: details.name,
+                game_id: details.game.id,
+                game_name: details.game.displayName,
+                game_slug: details.game.slug,
+                channels: channel_logins(&details.allow.channels),
+                ends_at: parse_time(&details.endAt),
+            });
+        }
+
+        let order = |target: &Target| (self.game_rank(&target.game_name), target.ends_at.unwrap_or(DateTime::<Utc>::MAX_UTC));
+        in_progress.sort_by_key(order);
+        not_started.sort_by_key(order);
+        in_progress.extend(not_started);
+        Ok(in_progress)
+    }
+
+    /// Position of `game` in [`MinerConfig::games`], or `None` if it should not be mined.
+    fn game_rank(&self, game: &str) -> Option<usize> {
+        if self.config.games.is_empty() {
+            return Some(0);
+        }
+        self.config.games.iter().position(|wanted| wanted.eq_ignore_ascii_case(game))
+    }
+
+    /// Finds a live channel streaming the campaign's game, among the channels it allows.
+    async fn find_channel(&self, target: &Target) -> Result<Option<Channel>, TwitchError> {
+        if !target.channels.is_empty() {
+            let logins: Vec<&str> = target.channels.iter().map(String::as_str).collect();
+            let streams = self.client.get_stream_info_batch(&logins).await?;
+            for stream in streams {
+                let info = match stream {
+                    Ok(info) => info,
+                    Err(StreamInfoError::ChannelNotFound) => continue,
+                    Err(StreamInfoError::TwitchError(e)) => return Err(e),
+                };
+                if let Some(live) = &info.stream && info.broadcastSettings.game.id == target.game_id {
+                    return Ok(Some(Channel { login: info.login.clone(), channel_id: info.id.clone(), broadcast_id: live.id.clone() }));
+                }
+            }
+            return Ok(None);
+        }
+        let streams = match self.client.get_game_directory(&target.game_slug, self.config.directory_limit, true).await {
+            Ok(streams) => streams,
+            Err(GameDirectoryError::NoStreamsFound(_)) => return Ok(None),
+            Err(GameDirectoryError::TwitchError(e)) => return Err(e),
+        };
+        Ok(streams.into_iter()
+            .find(|stream| stream.game.id == target.game_id)
+            .map(|stream| Channel { login: stream.broadcaster.login, channel_id: stream.broadcaster.id, broadcast_id: stream.id }))
+    }
+
+    /// Watches `channel` until the current drop is done, or returns why the channel had to be left.
+    async fn watch(&self, target: &Target, mut channel: Channel) -> Result<Option<SwitchReason>, TwitchError> {
+        let mut had_progress = false;
+        let mut watches_without_progress = 0;
+        loop {
+            self.client.send_watch(&channel.login, &channel.broadcast_id, &channel.channel_id, Some(&target.game_name), Some(&target.game_id)).await?;
+            let progress = match self.client.get_current_drop_progress_on_channel(&channel.login).await {
+                Ok(progress) => Some(progress),
+                // No drop session on this channel (yet).
+                Err(TwitchError::MissingField(_)) => None,
+                Err(e) => return Err(e),
+            };
+            let finished = match &progress {
+                Some(p) => p.currentMinutesWatched >= p.requiredMinutesWatched,
+                // The drop session ended, most likely because its drop was earned.
+                None if had_progress => true,
+                None => false,
+            };
+            had_progress |= progress.is_some();
+            if progress.is_none() {
+                watches_without_progress += 1;
+            }
+            self.emit(MinerEvent::Watched { login: channel.login.clone(), progress });
+            sleep(self.config.watch_interval).await;
+            if finished {
+                return Ok(None);
+            }
+            // Twitch can take a few minutes to start a drop session, but not forever.
+            if !had_progress && watches_without_progress >= self.config.max_watches_without_progress {
+                return Ok(Some(SwitchReason::NoProgress));
+            }
+
+            let info = match self.client.get_stream_info(&channel.login).await {
+                Ok(info) => info,
+                Err(StreamInfoError::ChannelNotFound) => return Ok(Some(SwitchReason::Offline)),
+                Err(StreamInfoError::TwitchError(e)) => return Err(e),
+            };
+            let Some(stream) = info.stream else {
+                return Ok(Some(SwitchReason::Offline));
+            };
+            if info.broadcastSettings.game.id != target.game_id {
+                return Ok(Some(SwitchReason::GameChanged));
+            }
+            channel.broadcast_id = stream.id;
+        }
+    }
+
+    fn emit(&self, event: MinerEvent) {
+        for handler in &self.handlers {
+            handler(&event);
+        }
+    }
+}
+
+/// Whether an in-progress campaign still has a drop that watching can complete.
+fn has_minable_drop(campaign: &DropCampaignsInProgress) -> bool {
+    campaign.timeBasedDrops.iter().any(|drop| {
+        let progress = &drop.self_drop;
+        drop.requiredSubs == 0 && progress.hasPreconditionsMet && !progress.isClaimed
+            && progress.currentMinutesWatched < drop.requiredMinutesWatched
+    })
+}
+
+fn channel_logins(channels: &Option<Vec<Channels>>) -> Vec<String> {
+    channels.iter().flatten().map(|channel| channel.name.clone()).collect()
+}
+
+fn parse_time(time: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(time).ok().map(|time| time.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{error::Error, sync::Mutex};
+
+    use super::*;
+    use crate::{client_type::ClientType, mock::MockTwitch, structs::*};
+
+    /// Starts a mock serving an in-progress "Rivals Drops" campaign of three minutes, with the
+    /// `(login, channel ID)` channels live on its game and listed in its directory, in order.
+    async fn rivals_mock(channels: &[(&str, &str)]) -> Result<Arc<MockTwitch>, Box<dyn Error>> {
+        let mock = Arc::new(MockTwitch::start().await?);
+        let game = Game { id: "g1".into(), name: "Marvel Rivals".into(), slug: "marvel-rivals".into(), ..Default::default() };
+        let mut state = mock.state();
+        state.inventory.push(DropCampaignsInProgress {
+            id: "campaign".into(),
+            name: "Rivals Drops".into(),
+            status: "ACTIVE".into(),
+            game: InventoryGame { id: "g1".into(), slug: "marvel-rivals".into(), name: "Marvel Rivals".into(), ..Default::default() },
+            timeBasedDrops: vec![InventoryTimeBasedDrops {
+                id: "drop".into(),
+                requiredMinutesWatched: 3,
+                self_drop: InventorySelf { hasPreconditionsMet: true, ..Default::default() },
+                ..Default::default()
+            }],
+            ..Default::default()
+        });
+        for (login, channel_id) in channels {
+            state.streams.insert(login.to_string(), StreamInfo {
+                id: channel_id.to_string(),
+                login: login.to_string(),
+                broadcastSettings: BroadcastSettings { game: StreamGame { id: "g1".into(), ..Default::default() }, ..Default::default() },
+                stream: Some(Stream { id: format!("{login}-broadcast"), ..Default::default() }),
+                ..Default::default()
+            });
+            state.directories.entry("marvel-rivals".into()).or_default().push(GameDirectory {
+                id: format!("{login}-broadcast"),
+                broadcaster: Broadcaster { id: channel_id.to_string(), login: login.to_string(), ..Default::default() },
+                game: game.clone(),
+                ..Default::default()
+            });
+        }
+        drop(state);
+        Ok(mock)
+    }
+
+    #[tokio::test]
+    async fn miner_watches_live_channels_and_switches_when_offline() -> Result<(), Box<dyn Error>> {
+        let mock = rivals_mock(&[("first", "1"), ("second", "2")]).await?;
+        let client = mock.authorized_client(&ClientType::web()).await?;
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let idle = Arc::new(tokio::sync::Notify::new());
+        let config = MinerConfig { watch_interval: Duration::from_millis(10), idle_interval: Duration::from_millis(10), ..Default::default() };
+        let miner = Miner::new(client).with_config(config).on_event({
+            let (events, idle, mock) = (events.clone(), idle.clone(), mock.clone());
+            move |event| {
+                // The first channel goes offline after the first minute.
+                if matches!(event, MinerEvent::Watched { login, .. } if login == "first") {
+                    let mut state = mock.state();
+                    state.streams.get_mut("first").unwrap().stream = None;
+                    state.directories.get_mut("marvel-rivals").unwrap().retain(|stream| stream.broadcaster.login != "first");
+                }
+                if *event == MinerEvent::Idle {
+                    idle.notify_one();
+                }
+                events.lock().unwrap().push(event.clone());
+            }
+        });
+        tokio::time::timeout(Duration::from_secs(10), miner.run(idle.notified())).await??;
+
+        let events = events.lock().unwrap().clone();
+        let progress = |login: &str, minutes: Option<u64>| MinerEvent::Watched {
+            login: login.into(),
+            progress: minutes.map(|minutes| CurrentDrop {
+                currentMinutesWatched: minutes,
+                dropID: "drop".into(),
+                game: Some(CurrentGame { displayName: "Marvel Rivals".into(), id: "g1".into() }),
+                requiredMinutesWatched: 3,
+                ..Default::default()
+            }),
+        };
+        assert_eq!(events, [
+            MinerEvent::CampaignSelected { campaign_id: "campaign".into(), name: "Rivals Drops".into(), game: "Marvel Rivals".into() },
+            MinerEvent::ChannelSelected { login: "first".into(), channel_id: "1".into(), broadcast_id: "first-broadcast".into() },
+            progress("first", Some(1)),
+            MinerEvent::ChannelLost { login: "first".into(), reason: SwitchReason::Offline },
+            MinerEvent::CampaignSelected { campaign_id: "campaign".into(), name: "Rivals Drops".into(), game: "Marvel Rivals".into() },
+            MinerEvent::ChannelSelected { login: "second".into(), channel_id: "2".into(), broadcast_id: "second-broadcast".into() },
+            progress("second", Some(2)),
+            progress("second", None),
+            MinerEvent::Idle,
+            MinerEvent::Stopped,
+        ]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn miner_outlasts_outages_without_refetching_campaigns() -> Result<(), Box<dyn Error>> {
+        let mock = rivals_mock(&[("first", "1")]).await?;
+        let client = mock.authorized_client(&ClientType::web()).await?.with_retry_policy(RetryPolicy::none());
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let idle = Arc::new(tokio::sync::Notify::new());
+        let config = MinerConfig { watch_interval: Duration::from_millis(10), idle_interval: Duration::from_millis(10), ..Default::default() };
+        let miner = Miner::new(client).with_config(config).on_event({
+            let (events, idle, mock) = (events.clone(), idle.clone(), mock.clone());
+            move |event| {
+                // Twitch goes down right after the first minute.
+                if matches!(event, MinerEvent::Watched { progress: Some(progress), .. } if progress.currentMinutesWatched == 1) {
+                    mock.state().gql_outage = 1;
+                }
+                if *event == MinerEvent::Idle {
+                    idle.notify_one();
+                }
+                events.lock().unwrap().push(event.clone());
+            }
+        });
+        tokio::time::timeout(Duration::from_secs(10), miner.run(idle.notified())).await??;
+
+        let events = events.lock().unwrap().clone();
+        let minutes: Vec<_> = events.iter()
+            .filter_map(|event| match event {
+                MinerEvent::Watched { progress, .. } => Some(progress.as_ref().map(|progress| progress.currentMinutesWatched)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(minutes, [Some(1), Some(2), None]);
+        assert!(events.contains(&MinerEvent::RequestFailed { error: "HTTP error: 503".into() }));
+        assert_eq!(events.iter().filter(|event| matches!(event, MinerEvent::ChannelSelected { .. })).count(), 1);
+        assert_eq!(events.last(), Some(&MinerEvent::Stopped));
+        // The campaigns are fetched when mining starts and again once the drop is earned, not every minute.
+        let inventory_fetches = mock.requests().iter().flat_map(|request| request.operations()).filter(|operation| operation == "Inventory").count();
+        assert_eq!(inventory_fetches, 2);
+        Ok(())
+    }
+}
@@ -0,0 +1,524 @@
+use std::{collections::{HashMap, VecDeque}, io, sync::{Arc, Mutex, MutexGuard}};
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use tokio::{io::{AsyncReadExt, AsyncWriteExt}, net::{TcpListener, TcpStream}, task::JoinHandle};
+
+use crate::{TwitchClient, client_type::ClientType, endpoints::Endpoints, error::AuthError, structs::{AvailableDrops, CampaignDetails, ClaimCampaign, ClaimDrop, CurrentDrop, CurrentGame, DropCampaigns, DropCampaignsInProgress, DropType, Drops, GameDirectory, GetInventory, Inventory, StreamInfo}};
+
+/// Programmable state served by a [`MockTwitch`] server.
+///
+/// Every field can be changed while the server is running through [`MockTwitch::state`].
+#[derive(Debug, Clone)]
+pub struct MockState {
+    pub user_id: String,
+    pub login: String,
+    /// Access token issued by `/oauth2/token` and accepted by `/oauth2/validate` and `/gql`.
+    /// Change it to simulate an expired or revoked token.
+    pub access_token: String,
+    /// Refresh token issued by `/oauth2/token` and accepted for the `refresh_token` grant.
+    pub refresh_token: String,
+    /// Scopes granted to issued tokens. Set to the requested scopes by `/oauth2/device`.
+    pub scopes: Vec<String>,
+    /// Lifetime, in seconds, of issued access tokens.
+    pub token_expires_in: u64,
+    pub device_code: String,
+    pub user_code: String,
+    /// Polling interval, in seconds, returned by `/oauth2/device`.
+    pub device_interval: u64,
+    /// Whether the user has entered the code. Until then, `/oauth2/token` answers `authorization_pending`.
+    pub device_authorized: bool,
+    /// OAuth error codes answered to the next device code polls, e.g. `slow_down` or `access_denied`.
+    pub device_poll_errors: VecDeque<String>,
+    /// Campaigns returned by `ViewerDropsDashboard`.
+    pub campaigns: Vec<DropCampaigns>,
+    /// Campaign details returned by `DropCampaignDetails`, by campaign ID.
+    pub campaign_details: HashMap<String, CampaignDetails>,
+    /// Campaigns in progress returned by `Inventory`. Their progress advances on every `SendEvents`.
+    pub inventory: Vec<DropCampaignsInProgress>,
+    /// Minutes added to every unfinished drop of the inventory by each `SendEvents` call.
+    pub minutes_per_event: u64,
+    /// Forced `DropsPage_ClaimDropRewards` statuses, by drop instance ID.
+    pub claim_outcomes: HashMap<String, String>,
+    /// Channels returned by `VideoPlayerStreamInfoOverlayChannel`, by login.
+    pub streams: HashMap<String, StreamInfo>,
+    /// Streams returned by `DirectoryPage_Game`, by game slug.
+    pub directories: HashMap<String, Vec<GameDirectory>>,
+    /// Slugs returned by `DirectoryGameRedirect`, by game name.
+    pub slugs: HashMap<String, String>,
+    /// Drops returned by `DropsHighlightService_AvailableDrops`, by channel ID.
+    pub available_drops: HashMap<String, AvailableDrops>,
+    /// Access tokens revoked through `/oauth2/revoke`. They are rejected like unknown tokens.
+    pub revoked: Vec<String>,
+    /// Code `/oauth2/authorize` redirects with, exchanged by `/oauth2/token` for tokens.
+    pub auth_code: String,
+    /// Error `/oauth2/authorize` redirects with instead of a code, e.g. `access_denied`.
+    pub authorize_error: Option<String>,
+    /// PKCE challenge received by `/oauth2/authorize`, checked against the verifier sent to `/oauth2/token`.
+    pub code_challenge: Option<String>,
+    /// Number of upcoming `/gql` requests answered with 503 Service Unavailable, to simulate an outage.
+    pub gql_outage: u32,
+}
+
+impl Default for MockState {
+    fn default() -> Self {
+        MockState {
+            user_id: "12345678".to_string(),
+            login: "mock_viewer".to_string(),
+            access_token: "mock-access-token".to_string(),
+            refresh_token: "mock-refresh-token".to_string(),
+            token_expires_in: 14400,
+            scopes: Vec::new(),
+            device_code: "mock-device-code".to_string(),
+            user_code: "MOCKCODE".to_string(),
+            device_interval: 0,
+            device_authorized: true,
+            device_poll_errors: VecDeque::new(),
+            campaigns: Vec::new(),
+            campaign_details: HashMap::new(),
+            inventory: Vec::new(),
+            minutes_per_event: 1,
+            claim_outcomes: HashMap::new(),
+            streams: HashMap::new(),
+            directories: HashMap::new(),
+            slugs: HashMap::new(),
+            available_drops: HashMap::new(),
+            revoked: Vec::new(),
+            auth_code: "mock-auth-code".to_string(),
+            authorize_error: None,
+            gql_outage: 0,
+            code_challenge: None,
+        }
+    }
+}
+
+/// A request received by a [`MockTwitch`] server.
+#[derive(Debug, Clone)]
+pub struct MockRequest {
+    pub method: String,
+    /// Path without the query string, e.g. `/gql` or `/oauth2/token`.
+    pub path: String,
+    /// Query string without the leading `?`, empty if there is none.
+    pub query: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl MockRequest {
+    /// Returns the value of the header `name` (case-insensitive).
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_str())
+    }
+
+    /// GraphQL operation names sent in this request, in order.
+    pub fn operations(&self) -> Vec<String> {
+        let body = serde_json::from_str::<Value>(&self.body).unwrap_or_default();
+        let operations = match body {
+            Value::Array(operations) => operations,
+            operation => vec![operation],
+        };
+        operations.iter().filter_map(|o| o.get("operationName").and_then(|n| n.as_str()).map(str::to_string)).collect()
+    }
+}
+
+/// In-process stand-in for Twitch, for integration tests of code built on this crate.
+///
+/// It serves the OAuth device and authorization-code flows, token refresh and revocation (`/oauth2/device`,
+/// `/oauth2/authorize`, `/oauth2/token`, `/oauth2/validate`, `/oauth2/revoke`),
+/// `Client-Integrity` tokens, and every operation of [`operations`](crate::operations) on `/gql`
+/// from a programmable [`MockState`]. The server stops when the `MockTwitch` is dropped.
+///
+/// ```rust
+/// use twitch_gql_rs::{TwitchClient, client_type::ClientType, mock::MockTwitch};
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mock = MockTwitch::start().await?;
+/// mock.state().slugs.insert("Marvel Rivals".into(), "marvel-rivals".into());
+///
+/// let client = TwitchClient::new(&ClientType::web(), &None).await?.with_endpoints(mock.endpoints());
+/// assert_eq!(client.get_slug("Marvel Rivals").await?, "marvel-rivals");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct MockTwitch {
+    base_url: String,
+    state: Arc<Mutex<MockState>>,
+    requests: Arc<Mutex<Vec<MockRequest>>>,
+    server: JoinHandle<()>,
+}
+
+impl MockTwitch {
+    /// Starts a server with the default [`MockState`] on a free local port.
+    pub async fn start() -> io::Result<Self> {
+        MockTwitch::start_with(MockState::default()).await
+    }
+
+    /// Starts a server with the given state on a free local port.
+    pub async fn start_with(state: MockState) -> io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let base_url = format!("http://{}", listener.local_addr()?);
+        let state = Arc::new(Mutex::new(state));
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let server = tokio::spawn({
+            let state = state.clone();
+            let requests = requests.clone();
+            async move {
+                while let Ok((socket, _)) = listener.accept().await {
+                    tokio::spawn(handle(socket, state.clone(), requests.clone()));
+                }
+            }
+        });
+        Ok(MockTwitch { base_url, state, requests, server })
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Endpoints pointing a [`TwitchClient`](crate::TwitchClient) at this server.
+    pub fn endpoints(&self) -> Endpoints {
+        Endpoints::from_base_url(&self.base_url)
+    }
+
+    /// Locks the served state so it can be inspected or changed.
+    pub fn state(&self) -> MutexGuard<'_, MockState> {
+        self.state.lock().unwrap()
+    }
+
+    /// Requests received so far, in order.
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// Creates a client pointed at this server and logs it in through the device flow.
+    pub async fn authorized_client(&self, client_type: &ClientType) -> Result<TwitchClient, AuthError> {
+        let mut client = TwitchClient::new(client_type, &None).await?.with_endpoints(self.endpoints());
+        let device_auth = client.request_device_auth().await?;
+        client.auth(device_auth).await?;
+        Ok(client)
+    }
+}
+
+impl Drop for MockTwitch {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+async fn handle(mut socket: TcpStream, state: Arc<Mutex<MockState>>, requests: Arc<Mutex<Vec<MockRequest>>>) {
+    let Ok(Some(request)) = read_request(&mut socket).await else {
+        return;
+    };
+    let (status, content_type, body) = {
+        let mut state = state.lock().unwrap();
+        respond(&mut state, &request)
+    };
+    requests.lock().unwrap().push(request);
+    // Redirects carry their target in the body slot.
+    let (location, body) = if status == 302 { (format!("location: {}\r\n", body), String::new()) } else { (String::new(), body) };
+    let response = format!(
+        "HTTP/1.1 {} Mock\r\ncontent-type: {}\r\ncontent-length: {}\r\n{}connection: close\r\n\r\n{}",
+        status, content_type, body.len(), location, body
+    );
+    let _ = socket.write_all(response.as_bytes()).await;
+}
+
+/// Reads a single HTTP/1.1 request. Returns `None` if the connection closed before a full request arrived.
+pub(crate) async fn read_request(socket: &mut TcpStream) -> io::Result<Option<MockRequest>> {
+    let mut raw = Vec::new();
+    let mut buf = [0u8; 4096];
+    loop {
+        let n = socket.read(&mut buf).await?;
+        if n == 0 {
+            return Ok(None);
+        }
+        raw.extend_from_slice(&buf[..n]);
+        let Some(end) = raw.windows(4).position(|w| w == b"\r\n\r\n") else {
+            continue;
+        };
+        let head = String::from_utf8_lossy(&raw[..end]).to_string();
+        let mut lines = head.lines();
+        let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+        let method = request_line.next().unwrap_or_default().to_string();
+        let target = request_line.next().unwrap_or_default();
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let (path, query) = (path.to_string(), query.to_string());
+        let headers: Vec<(String, String)> = lines
+            .filter_map(|l| l.split_once(':'))
+            .map(|(n, v)| (n.trim().to_string(), v.trim().to_string()))
+            .collect();
+        let length = headers.iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case("content-length"))
+            .and_then(|(_, v)| v.parse::<usize>().ok())
+            .unwrap_or(0);
+        if raw.len() < end + 4 + length {
+            continue;
+        }
+        let body = String::from_utf8_lossy(&raw[end + 4..end + 4 + length]).to_string();
+        return Ok(Some(MockRequest { method, path, query, headers, body }));
+    }
+}
+
+/// Decodes an `application/x-www-form-urlencoded` body.
+pub(crate) fn parse_form(body: &str) -> HashMap<String, String> {
+    fn decode(s: &str) -> String {
+        let bytes = s.as_bytes();
+        let mut out = Vec::with_capacity(bytes.len());
+        let mut i = 0;
+        while i < bytes.len() {
+            match bytes[i] {
+                b'+' => out.push(b' '),
+                b'%' if i + 2 < bytes.len() => match std::str::from_utf8(&bytes[i + 1..i + 3]).ok().and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                    Some(b) => {
+                        out.push(b);
+                        i += 2;
+                    },
+                    None => out.push(b'%'),
+                },
+                b => out.push(b),
+            }
+            i += 1;
+        }
+        String::from_utf8_lossy(&out).to_string()
+    }
+    body.split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(k, v)| (decode(k), decode(v)))
+        .collect()
+}
+
+fn json_response(status: u16, body: Value) -> (u16, &'static str, String) {
+    (status, "application/json", body.to_string())
+}
+
+fn respond(state: &mut MockState, request: &MockRequest) -> (u16, &'static str, String) {
+    match (request.method.as_str(), request.path.as_str()) {
+        ("POST", "/oauth2/device") => {
+            let form = parse_form(&request.body);
+            state.scopes = form.get("scopes").map(|s| s.split_whitespace().map(str::to_string).collect()).unwrap_or_default();
+            json_response(200, json!({
+            "device_code": state.device_code,
+            "user_code": state.user_code,
+            "verification_uri": format!("https://www.twitch.tv/activate?device-code={}", state.user_code),
+            "interval": state.device_interval,
+            "expires_in": 1800,
+            }))
+        },
+        ("GET", "/oauth2/authorize") => {
+            let query = parse_form(&request.query);
+            let redirect_uri = query.get("redirect_uri").cloned().unwrap_or_default();
+            state.code_challenge = query.get("code_challenge").cloned();
+            state.scopes = query.get("scope").map(|s| s.split_whitespace().map(str::to_string).collect()).unwrap_or_default();
+            let outcome = match &state.authorize_error {
+                Some(error) => ("error", error.as_str()),
+                None => ("code", state.auth_code.as_str()),
+            };
+            let params = [outcome, ("state", query.get("state").map(String::as_str).unwrap_or_default())];
+            let location = reqwest::Url::parse_with_params(&redirect_uri, params).map(String::from).unwrap_or(redirect_uri);
+            (302, "text/plain", location)
+        },
+        ("POST", "/oauth2/token") => oauth_token(state, &parse_form(&request.body)),
+        ("POST", "/oauth2/revoke") => {
+            let token = parse_form(&request.body).remove("token").unwrap_or_default();
+            if token == state.access_token && !state.revoked.contains(&token) {
+                state.revoked.push(token);
+                (200, "text/plain", String::new())
+            } else {
+                json_response(400, json!({ "status": 400, "message": "Invalid token" }))
+            }
+        },
+        ("GET", "/oauth2/validate") => {
+            let token = request.header("Authorization").and_then(|a| a.strip_prefix("OAuth ")).unwrap_or_default();
+            if token == state.access_token && !state.revoked.iter().any(|revoked| revoked == token) {
+                json_response(200, json!({
+                    "client_id": request.header("Client-Id").unwrap_or_default(),
+                    "login": state.login,
+                    "scopes": state.scopes,
+                    "user_id": state.user_id,
+                    "expires_in": state.token_expires_in,
+                }))
+            } else {
+                json_response(401, json!({ "status": 401, "message": "invalid access token" }))
+            }
+        },
+        ("POST", "/integrity") => {
+            let expiration = chrono::Utc::now().timestamp_millis() + 16 * 60 * 60 * 1000;
+            json_response(200, json!({ "token": "mock-integrity-token", "expiration": expiration, "request_id": "mock" }))
+        },
+        ("POST", "/gql") if request.header("Authorization").is_some_and(|a| a != format!("OAuth {}", state.access_token) || state.revoked.iter().any(|revoked| a == format!("OAuth {revoked}"))) => {
+            json_response(401, json!({ "error": "Unauthorized", "status": 401, "message": "The \"Authorization\" token is invalid." }))
+        },
+        ("POST", "/gql") if state.gql_outage > 0 => {
+            state.gql_outage -= 1;
+            json_response(503, json!({ "error": "Service Unavailable", "status": 503, "message": "service unavailable" }))
+        },
+        ("POST", "/gql") => match serde_json::from_str::<Value>(&request.body) {
+            Ok(Value::Array(operations)) => json_response(200, Value::Array(operations.iter().map(|o| gql(state, o)).collect())),
+            Ok(operation) => json_response(200, gql(state, &operation)),
+            Err(_) => json_response(400, json!({ "error": "Bad Request", "status": 400, "message": "invalid JSON body" })),
+        },
+        ("POST", "/spade") => (204, "text/plain", String::new()),
+        ("GET", path) if path.starts_with("/api/channel/hls/") => (200, "application/vnd.apple.mpegurl", "#EXTM3U\n".to_string()),
+        _ => json_response(404, json!({ "error": "Not Found", "status": 404, "message": "unknown path" })),
+    }
+}
+
+fn oauth_token(state: &mut MockState, form: &HashMap<String, String>) -> (u16, &'static str, String) {
+    if form.get("grant_type").map(String::as_str) == Some("refresh_token") {
+        if form.get("refresh_token") != Some(&state.refresh_token) {
+            return json_response(400, json!({ "status": 400, "message": "Invalid refresh token" }));
+        }
+        return issue_tokens(state);
+    }
+    if form.get("grant_type").map(String::as_str) == Some("authorization_code") {
+        let challenge = form.get("code_verifier").map(|verifier| URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes())));
+        if form.get("code") != Some(&state.auth_code) || challenge != state.code_challenge {
+            return json_response(400, json!({ "status": 400, "message": "Invalid authorization code" }));
+        }
+        return issue_tokens(state);
+    }
+    if form.get("device_code") != Some(&state.device_code) {
+        return json_response(400, json!({ "status": 400, "message": "invalid device code" }));
+    }
+    if let Some(code) = state.device_poll_errors.pop_front() {
+        return json_response(400, json!({ "status": 400, "message": code }));
+    }
+    if !state.device_authorized {
+        return json_response(400, json!({ "status": 400, "message": "authorization_pending" }));
+    }
+    issue_tokens(state)
+}
+
+fn issue_tokens(state: &MockState) -> (u16, &'static str, String) {
+    json_response(200, json!({
+        "access_token": state.access_token,
+        "refresh_token": state.refresh_token,
+        "expires_in": state.token_expires_in,
+        "scope": state.scopes,
+        "token_type": "bearer",
+    }))
+}
+
+fn gql(state: &mut MockState, operation: &Value) -> Value {
+    let name = operation.get("operationName").and_then(|n| n.as_str()).unwrap_or_default();
+    let variables = operation.get("variables").cloned().unwrap_or_default();
+    let variable = |key: &str| variables.get(key).and_then(|v| v.as_str()).unwrap_or_default().to_string();
+    let data = match name {
+        "VideoPlayerStreamInfoOverlayChannel" => json!({ "user": state.streams.get(&variable("channel")) }),
+        "DropsPage_ClaimDropRewards" => {
+            let id = variables.pointer("/input/dropInstanceID").and_then(|v| v.as_str()).unwrap_or_default();
+            json!({ "claimDropRewards": claim(state, id) })
+        },
+        "Inventory" => json!({ "currentUser": GetInventory {
+            id: state.user_id.clone(),
+            inventory: Inventory { dropCampaignsInProgress: Some(state.inventory.clone()) },
+        } }),
+        "DropCurrentSessionContext" => json!({ "currentUser": { "id": state.user_id, "dropCurrentSession": current_drop(state) } }),
+        "ViewerDropsDashboard" => json!({ "currentUser": Drops {
+            user_id: state.user_id.clone(),
+            login: state.login.clone(),
+            dropCampaigns: state.campaigns.clone(),
+        } }),
+        "DropCampaignDetails" => json!({ "user": { "id": state.user_id, "dropCampaign": state.campaign_details.get(&variable("dropID")) } }),
+        "DropsHighlightService_AvailableDrops" => json!({ "channel": state.available_drops.get(&variable("channelID")) }),
+        "PlaybackAccessToken" => json!({ "streamPlaybackAccessToken": { "value": json!({ "channel": variable("login") }).to_string(), "signature": "mock-signature" } }),
+        "DirectoryPage_Game" => json!({ "game": state.directories.get(&variable("slug")).map(|streams| json!({
+            "streams": { "edges": streams.iter().map(|node| json!({ "node": node })).collect::<Vec<_>>() }
+        })) }),
+        "DirectoryGameRedirect" => json!({ "game": state.slugs.get(&variable("name")).map(|slug| json!({ "slug": slug })) }),
+        "SendEvents" => {
+            advance_progress(state);
+            json!({ "sendSpadeEvents": { "statusCode": 204 } })
+        },
+        _ => return json!({ "errors": [{ "message": "PersistedQueryNotFound" }] }),
+    };
+    json!({ "data": data, "extensions": { "operationName": name } })
+}
+
+/// Credits watched minutes to every unfinished drop, and hands out a drop instance ID once one is complete.
+fn advance_progress(state: &mut MockState) {
+    let minutes = state.minutes_per_event;
+    let user_id = state.user_id.clone();
+    for campaign in &mut state.inventory {
+        for drop in &mut campaign.timeBasedDrops {
+            let progress = &mut drop.self_drop;
+            if progress.isClaimed || progress.currentMinutesWatched >= drop.requiredMinutesWatched {
+                continue;
+            }
+            progress.currentMinutesWatched = (progress.currentMinutesWatched + minutes).min(drop.requiredMinutesWatched);
+            if progress.currentMinutesWatched == drop.requiredMinutesWatched {
+                progress.dropInstanceID = Some(format!("{}#{}#{}", user_id, campaign.id, drop.id));
+            }
+        }
+    }
+}
+
+fn current_drop(state: &MockState) -> Option<CurrentDrop> {
+    state.inventory.iter().find_map(|campaign| {
+        let drop = campaign.timeBasedDrops.iter().find(|d| !d.self_drop.isClaimed && d.self_drop.currentMinutesWatched < d.requiredMinutesWatched)?;
+        Some(CurrentDrop {
+            channel: None,
+            currentMinutesWatched: drop.self_drop.currentMinutesWatched,
+            dropID: drop.id.clone(),
+            game: Some(CurrentGame { displayName: campaign.game.name.clone(), id: campaign.game.id.clone() }),
+            requiredMinutesWatched: drop.requiredMinutesWatched,
+        })
+    })
+}
+
+fn claim(state: &mut MockState, drop_instance_id: &str) -> ClaimDrop {
+    let forced = state.claim_outcomes.get(drop_instance_id).cloned();
+    let found = state.inventory.iter_mut().find_map(|campaign| {
+        let drop = campaign.timeBasedDrops.iter_mut().find(|d| d.self_drop.dropInstanceID.as_deref() == Some(drop_instance_id))?;
+        let status = match &forced {
+            Some(status) => status.clone(),
+            None if drop.self_drop.isClaimed => "DROP_INSTANCE_ALREADY_CLAIMED".to_string(),
+            None => "ELIGIBLE_FOR_ALL".to_string(),
+        };
+        if status == "ELIGIBLE_FOR_ALL" {
+            drop.self_drop.isClaimed = true;
+        }
+        let campaign = ClaimCampaign { detailsURL: campaign.detailsURL.clone(), id: campaign.id.clone(), status: Some(campaign.status.clone()) };
+        Some((status, DropType { campaign, id: drop.id.clone() }))
+    });
+    let (status, drop_type) = found.unwrap_or_else(|| (forced.unwrap_or_else(|| "DROP_INSTANCE_NOT_FOUND".to_string()), DropType::default()));
+    ClaimDrop { isUserAccountConnected: true, status, dropType: drop_type }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use super::*;
+    use crate::{error::ClaimDropError, structs::InventoryTimeBasedDrops};
+
+    #[tokio::test]
+    async fn mock_server_drives_device_flow_and_drop_progress() -> Result<(), Box<dyn Error>> {
+        let mock = MockTwitch::start().await?;
+        mock.state().inventory.push(DropCampaignsInProgress {
+            id: "campaign".into(),
+            timeBasedDrops: vec![InventoryTimeBasedDrops { id: "drop".into(), requiredMinutesWatched: 2, ..Default::default() }],
+            ..Default::default()
+        });
+        let client = mock.authorized_client(&ClientType::web()).await?;
+        assert_eq!(client.login.as_deref(), Some("mock_viewer"));
+
+        let user_id = client.user_id.clone().unwrap();
+        for _ in 0..2 {
+            client.send_watch("channel", "broadcast", "channel_id", None, None).await?;
+        }
+        let inventory = client.get_inventory().await?;
+        let progress = &inventory.inventory.dropCampaignsInProgress.unwrap()[0].timeBasedDrops[0].self_drop;
+        assert_eq!(progress.currentMinutesWatched, 2);
+        let instance = progress.dropInstanceID.clone().unwrap();
+        assert_eq!(instance, format!("{user_id}#campaign#drop"));
+
+        assert_eq!(client.claim_drop(&instance).await?.status, "ELIGIBLE_FOR_ALL");
+        assert!(matches!(client.claim_drop(&instance).await, Err(ClaimDropError::DropAlreadyClaimed)));
+        assert!(mock.requests().iter().any(|r| r.operations() == ["DropsPage_ClaimDropRewards"]));
+        Ok(())
+    }
+}
@@ -0,0 +1,408 @@
+use std::{collections::HashMap, sync::{Arc, RwLock}};
+
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::{scope::Scope, structs::*};
+
+/// A typed Twitch GraphQL operation that can be run with [`TwitchClient::execute`](crate::TwitchClient::execute).
+///
+/// Implement it for your own marker type to call operations this crate does not wrap:
+///
+/// ```rust,no_run
+/// use serde::{Deserialize, Serialize};
+/// use twitch_gql_rs::{operations::GqlOperation, TwitchClient};
+///
+/// #[derive(Serialize)]
+/// struct ChannelVariables { login: String }
+///
+/// #[derive(Deserialize)]
+/// struct Channel { id: String }
+///
+/// struct ChannelIdQuery;
+///
+/// impl GqlOperation for ChannelIdQuery {
+///     const NAME: &'static str = "ChannelId";
+///     const QUERY: Option<&'static str> = Some("query ChannelId($login: String!) { user(login: $login) { id } }");
+///     const RESPONSE_PATH: &'static [&'static str] = &["data", "user"];
+///     type Variables = ChannelVariables;
+///     type Response = Channel;
+/// }
+///
+/// # async fn example(client: &TwitchClient) -> Result<(), Box<dyn std::error::Error>> {
+/// let channel = client.execute::<ChannelIdQuery>(ChannelVariables { login: "twitch".into() }).await?;
+/// println!("{}", channel.id);
+/// # Ok(())
+/// # }
+/// ```
+pub trait GqlOperation {
+    /// Value sent as `operationName`.
+    const NAME: &'static str;
+    /// sha256 hash of the persisted query, if Twitch knows this operation as a persisted query.
+    const HASH: Option<&'static str> = None;
+    /// Full query document. Sent when there is no persisted hash, or when Twitch does not know the hash.
+    const QUERY: Option<&'static str> = None;
+    /// Whether the operation changes state on Twitch. Mutations are only retried when the
+    /// [`RetryPolicy`](crate::retry::RetryPolicy) opts them in by name.
+    const MUTATION: bool = false;
+    /// Whether the request must carry a `Client-Integrity` header.
+    /// Operations without it still get one if Twitch answers `failed integrity check`.
+    const REQUIRES_INTEGRITY: bool = false;
+    /// Scopes the session must have been granted. Checked before the request is sent.
+    const REQUIRED_SCOPES: &'static [Scope] = &[];
+    /// Path from the response root to the value deserialized into [`GqlOperation::Response`].
+    const RESPONSE_PATH: &'static [&'static str];
+    /// Variables sent with the operation.
+    type Variables: Serialize;
+    /// Type the value at [`GqlOperation::RESPONSE_PATH`] is deserialized into.
+    type Response: DeserializeOwned;
+}
+
+/// Runtime overrides of persisted query hashes and query documents, keyed by operation name.
+///
+/// When Twitch rotates a hash, register the new one here instead of waiting for a crate release.
+/// Clones share the same registry, so an override made through one clone of a
+/// [`TwitchClient`](crate::TwitchClient) is seen by all of them.
+///
+/// If Twitch answers `PersistedQueryNotFound`, the operation is retried once with its query
+/// document (the registered one, else [`GqlOperation::QUERY`]) when one is known.
+#[derive(Debug, Default, Clone)]
+pub struct PersistedQueries {
+    entries: Arc<RwLock<HashMap<String, PersistedQueryEntry>>>,
+}
+
+#[derive(Debug, Default, Clone)]
+struct PersistedQueryEntry {
+    hash: Option<String>,
+    query: Option<String>,
+}
+
+impl PersistedQueries {
+    /// Overrides the sha256 hash sent for `operation_name`.
+    pub fn set_hash(&self, operation_name: &str, sha256: &str) {
+        self.entries.write().unwrap().entry(operation_name.to_string()).or_default().hash = Some(sha256.to_string());
+    }
+
+    /// Overrides the query document used for `operation_name`.
+    pub fn set_query(&self, operation_name: &str, query: &str) {
+        self.entries.write().unwrap().entry(operation_name.to_string()).or_default().query = Some(query.to_string());
+    }
+
+    /// Removes every override for `operation_name`, going back to the built-in hash and query.
+    pub fn reset(&self, operation_name: &str) {
+        self.entries.write().unwrap().remove(operation_name);
+    }
+
+    /// The overridden hash for `operation_name`, if any.
+    pub fn hash(&self, operation_name: &str) -> Option<String> {
+        self.entries.read().unwrap().get(operation_name).and_then(|e| e.hash.clone())
+    }
+
+    /// The overridden query document for `operation_name`, if any.
+    pub fn query(&self, operation_name: &str) -> Option<String> {
+        self.entries.read().unwrap().get(operation_name).and_then(|e| e.query.clone())
+    }
+}
+
+//get_stream_info
+/// `VideoPlayerStreamInfoOverlayChannel` - current stream information for a channel.
+pub struct StreamInfoQuery;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct StreamInfoVariables {
+    pub channel: String,
+}
+
+impl GqlOperation for StreamInfoQuery {
+    const NAME: &'static str = "VideoPlayerStreamInfoOverlayChannel";
+    const HASH: Option<&'static str> = Some("198492e0857f6aedead9665c81c5a06d67b25b58034649687124083ff288597d");
+    const RESPONSE_PATH: &'static [&'static str] = &["data", "user"];
+    type Variables = StreamInfoVariables;
+    type Response = StreamInfo;
+}
+
+//claim_drop
+/// `DropsPage_ClaimDropRewards` - claims a drop instance.
+pub struct ClaimDropRewardsMutation;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ClaimDropRewardsVariables {
+    pub input: ClaimDropRewardsInput,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug, Clone)]
+pub struct ClaimDropRewardsInput {
+    pub dropInstanceID: String,
+}
+
+impl GqlOperation for ClaimDropRewardsMutation {
+    const NAME: &'static str = "DropsPage_ClaimDropRewards";
+    const MUTATION: bool = true;
+    const REQUIRES_INTEGRITY: bool = true;
+    const HASH: Option<&'static str> = Some("a455deea71bdc9015b78eb49f4acfbce8baa7ccbedd28e549bb025bd0f751930");
+    const RESPONSE_PATH: &'static [&'static str] = &["data", "claimDropRewards"];
+    type Variables = ClaimDropRewardsVariables;
+    type Response = ClaimDrop;
+}
+
+//get_inventory
+/// `Inventory` - the user's drops inventory.
+pub struct InventoryQuery;
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct InventoryVariables {
+    pub fetchRewardCampaigns: bool,
+}
+
+impl GqlOperation for InventoryQuery {
+    const NAME: &'static str = "Inventory";
+    const HASH: Option<&'static str> = Some("d86775d0ef16a63a33ad52e80eaff963b2d5b72fada7c991504a57496e1d8e4b");
+    const RESPONSE_PATH: &'static [&'static str] = &["data", "currentUser"];
+    type Variables = InventoryVariables;
+    type Response = GetInventory;
+}
+
+//get_current_drop_progress_on_channel
+/// `DropCurrentSessionContext` - drop progress for the user on a channel.
+pub struct DropCurrentSessionContextQuery;
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug, Clone)]
+pub struct DropCurrentSessionContextVariables {
+    pub channelLogin: String,
+}
+
+impl GqlOperation for DropCurrentSessionContextQuery {
+    const NAME: &'static str = "DropCurrentSessionContext";
+    const HASH: Option<&'static str> = Some("4d06b702d25d652afb9ef835d2a550031f1cf762b193523a92166f40ea3d142b");
+    const RESPONSE_PATH: &'static [&'static str] = &["data", "currentUser", "dropCurrentSession"];
+    type Variables = DropCurrentSessionContextVariables;
+    type Response = CurrentDrop;
+}
+
+//get_campaign
+/// `ViewerDropsDashboard` - drop campaigns available to the user.
+pub struct ViewerDropsDashboardQuery;
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct ViewerDropsDashboardVariables {
+    pub fetchRewardCampaigns: bool,
+}
+
+impl GqlOperation for ViewerDropsDashboardQuery {
+    const NAME: &'static str = "ViewerDropsDashboard";
+    const HASH: Option<&'static str> = Some("5a4da2ab3d5b47c9f9ce864e727b2cb346af1e3ea8b897fe8f704a97ff017619");
+    const RESPONSE_PATH: &'static [&'static str] = &["data", "currentUser"];
+    type Variables = ViewerDropsDashboardVariables;
+    type Response = Drops;
+}
+
+//get_campaign_details
+/// `DropCampaignDetails` - full details of a drop campaign.
+pub struct DropCampaignDetailsQuery;
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug, Clone)]
+pub struct DropCampaignDetailsVariables {
+    pub channelLogin: String,
+    pub dropID: String,
+}
+
+impl GqlOperation for DropCampaignDetailsQuery {
+    const NAME: &'static str = "DropCampaignDetails";
+    const HASH: Option<&'static str> = Some("039277bf98f3130929262cc7c6efd9c141ca3749cb6dca442fc8ead9a53f77c1");
+    const RESPONSE_PATH: &'static [&'static str] = &["data", "user", "dropCampaign"];
+    type Variables = DropCampaignDetailsVariables;
+    type Response = CampaignDetails;
+}
+
+//get_available_drops_for_channel
+/// `DropsHighlightService_AvailableDrops` - drops available on a channel.
+pub struct AvailableDropsQuery;
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug, Clone)]
+pub struct AvailableDropsVariables {
+    pub channelID: String,
+}
+
+impl GqlOperation for AvailableDropsQuery {
+    const NAME: &'static str = "DropsHighlightService_AvailableDrops";
+    const HASH: Option<&'static str> = Some("782dad0f032942260171d2d80a654f88bdd0c5a9dddc392e9bc92218a0f42d20");
+    const RESPONSE_PATH: &'static [&'static str] = &["data", "channel"];
+    type Variables = AvailableDropsVariables;
+    type Response = AvailableDrops;
+}
+
+//get_playback_access_token
+/// `PlaybackAccessToken` - signed token for a channel's HLS playlist.
+pub struct PlaybackAccessTokenQuery;
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug, Clone)]
+pub struct PlaybackAccessTokenVariables {
+    pub isLive: bool,
+    pub isVod: bool,
+    pub login: String,
+    pub platform: String,
+    pub playerType: String,
+    pub vodID: String,
+}
+
+impl PlaybackAccessTokenVariables {
+    /// Variables for a live stream watched from the web player.
+    pub fn live(channel_login: &str) -> Self {
+        PlaybackAccessTokenVariables {
+            isLive: true,
+            isVod: false,
+            login: channel_login.to_string(),
+            platform: "web".to_string(),
+            playerType: "site".to_string(),
+            vodID: String::new(),
+        }
+    }
+}
+
+impl GqlOperation for PlaybackAccessTokenQuery {
+    const NAME: &'static str = "PlaybackAccessToken";
+    const HASH: Option<&'static str> = Some("ed230aa1e33e07eebb8928504583da78a5173989fadfb1ac94be06a04f3cdbe9");
+    const QUERY: Option<&'static str> = Some(r#"
+        query PlaybackAccessToken($login: String!, $isLive: Boolean!, $vodID: ID!, $isVod: Boolean!, $playerType: String!, $platform: String!) {
+            streamPlaybackAccessToken(channelName: $login, params: {platform: $platform, playerBackend: "mediaplayer", playerType: $playerType}) @include(if: $isLive) {
+                value
+                signature
+            }
+            videoPlaybackAccessToken(id: $vodID, params: {platform: $platform, playerBackend: "mediaplayer", playerType: $playerType}) @include(if: $isVod) {
+                value
+                signature
+            }
+        }
+    "#);
+    const RESPONSE_PATH: &'static [&'static str] = &["data", "streamPlaybackAccessToken"];
+    type Variables = PlaybackAccessTokenVariables;
+    type Response = PlaybackAccessToken;
+}
+
+//get_game_directory
+/// `DirectoryPage_Game` - live streams for a game.
+pub struct DirectoryPageGameQuery;
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug, Clone)]
+pub struct DirectoryPageGameVariables {
+    pub limit: u64,
+    pub slug: String,
+    pub imageWidth: u64,
+    pub includeCostreaming: bool,
+    pub options: DirectoryOptions,
+    pub sortTypeIsRecency: bool,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug, Clone)]
+pub struct DirectoryOptions {
+    pub broadcasterLanguages: Vec<String>,
+    pub freeformTags: Option<Vec<String>>,
+    pub includeRestricted: Vec<String>,
+    pub recommendationsContext: RecommendationsContext,
+    pub sort: String,
+    pub systemFilters: Vec<String>,
+    pub tags: Vec<String>,
+    pub requestID: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct RecommendationsContext {
+    pub platform: String,
+}
+
+impl DirectoryPageGameVariables {
+    /// Variables for the first `limit` streams of a game sorted by relevance.
+    pub fn new(game_slug: &str, limit: u64, drops_enabled: bool) -> Self {
+        let filter = if drops_enabled { "DROPS_ENABLED" } else { "" };
+        DirectoryPageGameVariables {
+            limit,
+            slug: game_slug.to_string(),
+            imageWidth: 50,
+            includeCostreaming: false,
+            options: DirectoryOptions {
+                broadcasterLanguages: Vec::new(),
+                freeformTags: None,
+                includeRestricted: vec!["SUB_ONLY_LIVE".to_string()],
+                recommendationsContext: RecommendationsContext { platform: "web".to_string() },
+                sort: "RELEVANCE".to_string(),
+                systemFilters: vec![filter.to_string()],
+                tags: Vec::new(),
+                requestID: "JIRA-VXP-2397".to_string(),
+            },
+            sortTypeIsRecency: false,
+        }
+    }
+}
+
+impl GqlOperation for DirectoryPageGameQuery {
+    const NAME: &'static str = "DirectoryPage_Game";
+    const HASH: Option<&'static str> = Some("cb5dc816e139dcb8a118f14b4b677d59abc224a4b016c4bc2bb00a47fe0ddec4");
+    const RESPONSE_PATH: &'static [&'static str] = &["data", "game", "streams"];
+    type Variables = DirectoryPageGameVariables;
+    type Response = GameDirectoryStreams;
+}
+
+//get_slug
+/// `DirectoryGameRedirect` - slug of a game by its name.
+pub struct DirectoryGameRedirectQuery;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct DirectoryGameRedirectVariables {
+    pub name: String,
+}
+
+impl GqlOperation for DirectoryGameRedirectQuery {
+    const NAME: &'static str = "DirectoryGameRedirect";
+    const HASH: Option<&'static str> = Some("1f0300090caceec51f33c5e20647aceff9017f740f223c3c532ba6fa59f6b6cc");
+    const QUERY: Option<&'static str> = Some(r#"
+        query DirectoryGameRedirect($name: String!) {
+            game(name: $name) {
+                id
+                slug
+            }
+        }
+    "#);
+    const RESPONSE_PATH: &'static [&'static str] = &["data", "game", "slug"];
+    type Variables = DirectoryGameRedirectVariables;
+    type Response = String;
+}
+
+//send_watch
+/// `SendEvents` - uploads spade events (e.g. `minute-watched`) through GQL.
+pub struct SendEventsMutation;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SendEventsVariables {
+    pub input: SendSpadeEventsInput,
+}
+
+/// Spade events as a gzip-compressed, base64-encoded JSON array.
+#[derive(Serialize, Debug, Clone)]
+pub struct SendSpadeEventsInput {
+    pub data: String,
+    pub repository: String,
+    pub encoding: String,
+}
+
+impl GqlOperation for SendEventsMutation {
+    const NAME: &'static str = "SendEvents";
+    const MUTATION: bool = true;
+    const QUERY: Option<&'static str> = Some(r#"
+        mutation SendEvents($input: SendSpadeEventsInput!) {
+            sendSpadeEvents(input: $input) {
+                statusCode
+            }
+        }
+    "#);
+    const RESPONSE_PATH: &'static [&'static str] = &["data", "sendSpadeEvents", "statusCode"];
+    type Variables = SendEventsVariables;
+    type Response = u64;
+}
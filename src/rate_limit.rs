@@ -0,0 +1,69 @@
+use std::{sync::{Arc, Mutex}, time::Duration};
+
+use tokio::time::{Instant, sleep};
+
+/// Token-bucket rate limiter for requests sent to Twitch.
+///
+/// Cloning a `RateLimiter` does not create a new bucket: all clones draw from the same tokens.
+/// A limiter set on a [`TwitchClient`](crate::TwitchClient) is therefore shared by every clone
+/// of that client, and one limiter can be handed to several accounts that go through the same proxy.
+///
+/// ```rust
+/// use twitch_gql_rs::rate_limit::RateLimiter;
+///
+/// // 5 requests per second on average, with bursts of up to 10 requests.
+/// let limiter = RateLimiter::new(5.0, 10);
+/// ```
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    rate: f64,
+    burst: f64,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl RateLimiter {
+    /// Creates a limiter allowing `requests_per_second` on average and up to `burst` requests at once.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `requests_per_second` is not positive or `burst` is zero.
+    pub fn new(requests_per_second: f64, burst: u32) -> Self {
+        assert!(requests_per_second > 0.0, "requests_per_second must be positive");
+        assert!(burst > 0, "burst must be at least 1");
+        RateLimiter {
+            bucket: Arc::new(Mutex::new(Bucket {
+                rate: requests_per_second,
+                burst: burst as f64,
+                tokens: burst as f64,
+                updated: Instant::now(),
+            })),
+        }
+    }
+
+    /// Waits until a request may be sent and takes a token for it.
+    pub async fn acquire(&self) {
+        let wait = {
+            let mut bucket = self.bucket.lock().unwrap();
+            let now = Instant::now();
+            let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+            bucket.tokens = (bucket.tokens + elapsed * bucket.rate).min(bucket.burst);
+            bucket.updated = now;
+            // Reserve the token now, even if it is only available later, so waiters are served in order.
+            bucket.tokens -= 1.0;
+            if bucket.tokens >= 0.0 {
+                Duration::ZERO
+            } else {
+                Duration::from_secs_f64(-bucket.tokens / bucket.rate)
+            }
+        };
+        if !wait.is_zero() {
+            sleep(wait).await;
+        }
+    }
+}
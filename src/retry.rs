@@ -0,0 +1,98 @@
+use std::{collections::HashSet, time::Duration};
+
+use serde_json::Value;
+
+/// Decides which failed requests are sent again and how long to wait in between.
+///
+/// Applies to every GQL and OAuth request of a [`TwitchClient`](crate::TwitchClient).
+/// Mutations (e.g. `DropsPage_ClaimDropRewards`, `SendEvents`) are never retried
+/// unless their operation name is listed in [`RetryPolicy::retryable_mutations`].
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first one. `1` disables retries.
+    pub max_attempts: u32,
+    /// Delay before the first retry.
+    pub initial_backoff: Duration,
+    /// Upper bound for the delay between attempts.
+    pub max_backoff: Duration,
+    /// Factor the delay is multiplied by after every attempt.
+    pub multiplier: f64,
+    /// Random spread applied to each delay, from `0.0` (none) to `1.0` (up to ±100%).
+    pub jitter: f64,
+    /// HTTP statuses that are considered transient.
+    pub retryable_statuses: HashSet<u16>,
+    /// GraphQL error messages that are considered transient (case-insensitive).
+    pub retryable_graphql_errors: HashSet<String>,
+    /// Mutations that may be retried, by operation name.
+    pub retryable_mutations: HashSet<String>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(10),
+            multiplier: 2.0,
+            jitter: 0.2,
+            retryable_statuses: [429, 500, 502, 503, 504].into_iter().collect(),
+            retryable_graphql_errors: ["service error", "service timeout", "service unavailable"].into_iter().map(String::from).collect(),
+            retryable_mutations: HashSet::new(),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that never retries.
+    pub fn none() -> Self {
+        RetryPolicy { max_attempts: 1, ..RetryPolicy::default() }
+    }
+
+    /// Allows the mutation `operation_name` to be retried.
+    pub fn with_retryable_mutation(mut self, operation_name: &str) -> Self {
+        self.retryable_mutations.insert(operation_name.to_string());
+        self
+    }
+
+    /// Whether an operation may be retried at all.
+    pub(crate) fn allows(&self, operation_name: &str, mutation: bool) -> bool {
+        !mutation || self.retryable_mutations.contains(operation_name)
+    }
+
+    /// Whether a response with this status and body should be retried.
+    pub(crate) fn is_retryable_response(&self, status: u16, body: &str) -> bool {
+        if self.retryable_statuses.contains(&status) {
+            return true;
+        }
+        if self.retryable_graphql_errors.is_empty() || !body.contains("errors") {
+            return false;
+        }
+        let Ok(json) = serde_json::from_str::<Value>(body) else {
+            return false;
+        };
+        let responses = match &json {
+            Value::Array(items) => items.iter().collect(),
+            other => vec![other],
+        };
+        responses.iter()
+            .filter_map(|r| r.get("errors").and_then(|e| e.as_array()))
+            .flatten()
+            .filter_map(|e| e.get("message").and_then(|m| m.as_str()))
+            .any(|message| self.retryable_graphql_errors.iter().any(|r| r.eq_ignore_ascii_case(message)))
+    }
+
+    /// Whether a transport error is worth another attempt.
+    pub(crate) fn is_transient(error: &reqwest::Error) -> bool {
+        error.is_timeout() || error.is_connect() || error.is_request() || error.is_body()
+    }
+
+    /// Delay to wait after the given (1-based) failed attempt.
+    pub(crate) fn backoff(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let delay = self.initial_backoff.as_secs_f64() * self.multiplier.max(1.0).powi(exponent);
+        let delay = delay.min(self.max_backoff.as_secs_f64());
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        let factor = if jitter > 0.0 { rand::random_range(1.0 - jitter..=1.0 + jitter) } else { 1.0 };
+        Duration::from_secs_f64((delay * factor).max(0.0))
+    }
+}
@@ -0,0 +1,103 @@
+use std::{convert::Infallible, fmt, str::FromStr};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// OAuth scope requested during device authorization and granted to a session.
+///
+/// ```rust
+/// use twitch_gql_rs::scope::Scope;
+///
+/// assert_eq!(Scope::ChatRead.as_str(), "chat:read");
+/// assert_eq!("user:read:follows".parse::<Scope>().unwrap(), Scope::UserReadFollows);
+/// assert_eq!("some:new:scope".parse::<Scope>().unwrap(), Scope::Other("some:new:scope".into()));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Scope {
+    /// `chat:read` - read chat messages over IRC.
+    ChatRead,
+    /// `chat:edit` - send chat messages over IRC.
+    ChatEdit,
+    /// `user:read:chat` - receive chat messages through EventSub.
+    UserReadChat,
+    /// `user:write:chat` - send chat messages through the API.
+    UserWriteChat,
+    /// `whispers:read` - read whispers.
+    WhispersRead,
+    /// `whispers:edit` - send whispers.
+    WhispersEdit,
+    /// `user:read:follows` - list followed channels.
+    UserReadFollows,
+    /// `user:edit:follows` - follow and unfollow channels.
+    UserEditFollows,
+    /// `user:read:subscriptions` - list the user's subscriptions.
+    UserReadSubscriptions,
+    /// `user:read:email` - read the user's email address.
+    UserReadEmail,
+    /// `channel:read:redemptions` - read channel points rewards and redemptions.
+    ChannelReadRedemptions,
+    /// `channel:manage:redemptions` - manage channel points rewards and redemptions.
+    ChannelManageRedemptions,
+    /// Any scope without a dedicated variant.
+    Other(String),
+}
+
+impl Scope {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Scope::ChatRead => "chat:read",
+            Scope::ChatEdit => "chat:edit",
+            Scope::UserReadChat => "user:read:chat",
+            Scope::UserWriteChat => "user:write:chat",
+            Scope::WhispersRead => "whispers:read",
+            Scope::WhispersEdit => "whispers:edit",
+            Scope::UserReadFollows => "user:read:follows",
+            Scope::UserEditFollows => "user:edit:follows",
+            Scope::UserReadSubscriptions => "user:read:subscriptions",
+            Scope::UserReadEmail => "user:read:email",
+            Scope::ChannelReadRedemptions => "channel:read:redemptions",
+            Scope::ChannelManageRedemptions => "channel:manage:redemptions",
+            Scope::Other(scope) => scope,
+        }
+    }
+}
+
+impl FromStr for Scope {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "chat:read" => Scope::ChatRead,
+            "chat:edit" => Scope::ChatEdit,
+            "user:read:chat" => Scope::UserReadChat,
+            "user:write:chat" => Scope::UserWriteChat,
+            "whispers:read" => Scope::WhispersRead,
+            "whispers:edit" => Scope::WhispersEdit,
+            "user:read:follows" => Scope::UserReadFollows,
+            "user:edit:follows" => Scope::UserEditFollows,
+            "user:read:subscriptions" => Scope::UserReadSubscriptions,
+            "user:read:email" => Scope::UserReadEmail,
+            "channel:read:redemptions" => Scope::ChannelReadRedemptions,
+            "channel:manage:redemptions" => Scope::ChannelManageRedemptions,
+            other => Scope::Other(other.to_string()),
+        })
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for Scope {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Scope {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let Ok(scope) = String::deserialize(deserializer)?.parse();
+        Ok(scope)
+    }
+}
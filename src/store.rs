@@ -0,0 +1,332 @@
+use std::{fmt, future::Future, path::{Path, PathBuf}, pin::Pin, sync::{Arc, Mutex}};
+
+use aes_gcm::{Aes256Gcm, Key, KeyInit, Nonce, aead::Aead};
+use argon2::Argon2;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use serde::{Deserialize, Serialize, de::Error as _};
+use serde_json::{Map, Value};
+use tokio::{fs, io::AsyncWriteExt};
+
+use crate::{TwitchClient, error::SystemError};
+
+/// Future returned by [`SessionStore`] methods.
+pub type StoreFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, SystemError>> + Send + 'a>>;
+
+/// Somewhere a serialized session is kept between runs.
+///
+/// Attach a store with [`TwitchClient::with_session_store`] and renewed tokens are written back
+/// to it automatically. Implement it to keep sessions in a keyring, a database or similar.
+///
+/// ```rust,no_run
+/// use std::{path::Path, sync::Arc};
+/// use twitch_gql_rs::{builder::TwitchClientBuilder, store::EncryptedFileStore};
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let store = Arc::new(EncryptedFileStore::new(Path::new("session.enc"), "correct horse battery staple"));
+/// let client = TwitchClientBuilder::from_store(store).await?.build()?;
+/// # Ok(())
+/// # }
+/// ```
+pub trait SessionStore: Send + Sync {
+    /// Returns the stored session, or `None` if nothing has been saved yet.
+    fn load(&self) -> StoreFuture<'_, Option<Vec<u8>>>;
+
+    /// Replaces the stored session.
+    fn save<'a>(&'a self, session: &'a [u8]) -> StoreFuture<'a, ()>;
+
+    /// Removes the stored session. Removing from an empty store succeeds.
+    fn delete(&self) -> StoreFuture<'_, ()>;
+}
+
+/// Stores the session as plain JSON in a file.
+///
+/// Writes go to a temporary file that replaces the old one, so a crash never leaves a truncated
+/// session behind. On Unix the file is only readable by its owner.
+#[derive(Debug, Clone)]
+pub struct FileStore {
+    path: PathBuf,
+}
+
+impl FileStore {
+    pub fn new(path: &Path) -> Self {
+        FileStore { path: path.to_path_buf() }
+    }
+}
+
+impl SessionStore for FileStore {
+    fn load(&self) -> StoreFuture<'_, Option<Vec<u8>>> {
+        Box::pin(read_if_exists(&self.path))
+    }
+
+    fn save<'a>(&'a self, session: &'a [u8]) -> StoreFuture<'a, ()> {
+        Box::pin(write_atomic(&self.path, session))
+    }
+
+    fn delete(&self) -> StoreFuture<'_, ()> {
+        Box::pin(remove_if_exists(&self.path))
+    }
+}
+
+/// Keeps the session in memory, e.g. for tests. Clones share the same session.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStore {
+    session: Arc<Mutex<Option<Vec<u8>>>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        MemoryStore::default()
+    }
+}
+
+impl SessionStore for MemoryStore {
+    fn load(&self) -> StoreFuture<'_, Option<Vec<u8>>> {
+        let session = self.session.lock().unwrap().clone();
+        Box::pin(async move { Ok(session) })
+    }
+
+    fn save<'a>(&'a self, session: &'a [u8]) -> StoreFuture<'a, ()> {
+        *self.session.lock().unwrap() = Some(session.to_vec());
+        Box::pin(async { Ok(()) })
+    }
+
+    fn delete(&self) -> StoreFuture<'_, ()> {
+        *self.session.lock().unwrap() = None;
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// Stores the session in a file encrypted with AES-256-GCM, using a key derived from a
+/// passphrase with Argon2id.
+///
+/// A fresh salt and nonce are used for every write. Files are written like [`FileStore`] does.
+#[derive(Clone)]
+pub struct EncryptedFileStore {
+    path: PathBuf,
+    passphrase: String,
+}
+
+impl fmt::Debug for EncryptedFileStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EncryptedFileStore").field("path", &self.path).finish_non_exhaustive()
+    }
+}
+
+/// On-disk layout of an [`EncryptedFileStore`].
+#[derive(Deserialize, Serialize)]
+struct EncryptedSession {
+    kdf: String,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+const KDF: &str = "argon2id";
+
+impl EncryptedFileStore {
+    pub fn new(path: &Path, passphrase: &str) -> Self {
+        EncryptedFileStore { path: path.to_path_buf(), passphrase: passphrase.to_string() }
+    }
+
+    fn cipher(&self, salt: &[u8]) -> Result<Aes256Gcm, SystemError> {
+        let mut key = [0u8; 32];
+        Argon2::default()
+            .hash_password_into(self.passphrase.as_bytes(), salt, &mut key)
+            .map_err(|e| SystemError::SessionDecryption(e.to_string()))?;
+        Ok(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)))
+    }
+
+    fn encrypt(&self, session: &[u8]) -> Result<Vec<u8>, SystemError> {
+        let salt: [u8; 16] = rand::random();
+        let nonce: [u8; 12] = rand::random();
+        let ciphertext = self.cipher(&salt)?
+            .encrypt(Nonce::from_slice(&nonce), session)
+            .map_err(|_| SystemError::SessionDecryption("encryption failed".into()))?;
+        let file = EncryptedSession {
+            kdf: KDF.to_string(),
+            salt: STANDARD.encode(salt),
+            nonce: STANDARD.encode(nonce),
+            ciphertext: STANDARD.encode(ciphertext),
+        };
+        serde_json::to_vec_pretty(&file).map_err(SystemError::SerializationProblem)
+    }
+
+    fn decrypt(&self, file: &[u8]) -> Result<Vec<u8>, SystemError> {
+        let file: EncryptedSession = serde_json::from_slice(file).map_err(SystemError::DeserializationProblem)?;
+        if file.kdf != KDF {
+            return Err(SystemError::SessionDecryption(format!("unsupported key derivation `{}`", file.kdf)));
+        }
+        let decode = |field: &str| STANDARD.decode(field).map_err(|e| SystemError::SessionDecryption(e.to_string()));
+        let (salt, nonce, ciphertext) = (decode(&file.salt)?, decode(&file.nonce)?, decode(&file.ciphertext)?);
+        if nonce.len() != 12 {
+            return Err(SystemError::SessionDecryption("invalid nonce".into()));
+        }
+        self.cipher(&salt)?
+            .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+            .map_err(|_| SystemError::SessionDecryption("wrong passphrase or corrupted file".into()))
+    }
+}
+
+impl SessionStore for EncryptedFileStore {
+    fn load(&self) -> StoreFuture<'_, Option<Vec<u8>>> {
+        Box::pin(async move {
+            match read_if_exists(&self.path).await? {
+                Some(file) => self.decrypt(&file).map(Some),
+                None => Ok(None),
+            }
+        })
+    }
+
+    fn save<'a>(&'a self, session: &'a [u8]) -> StoreFuture<'a, ()> {
+        Box::pin(async move { write_atomic(&self.path, &self.encrypt(session)?).await })
+    }
+
+    fn delete(&self) -> StoreFuture<'_, ()> {
+        Box::pin(remove_if_exists(&self.path))
+    }
+}
+
+/// Version of the saved session format written by this release.
+///
+/// Files without a `version` field were written before versioning and are treated as version 1.
+/// Fields added since then default through `#[serde(default)]`, so they did not need a new version.
+pub const SESSION_VERSION: u64 = 1;
+
+/// Upgrades a saved session by one version; `MIGRATIONS[n]` turns version `n + 1` into `n + 2`.
+///
+/// Bump [`SESSION_VERSION`] and append a step here when a field is renamed, removed or changes type.
+const MIGRATIONS: &[fn(&mut Map<String, Value>)] = &[];
+
+#[derive(Serialize)]
+struct VersionedSession<'a> {
+    version: u64,
+    #[serde(flatten)]
+    session: &'a TwitchClient,
+}
+
+/// Serializes a session for a [`SessionStore`], including tokens renewed in the background.
+pub(crate) fn encode_session(twitch: &TwitchClient) -> Result<Vec<u8>, SystemError> {
+    let mut session = twitch.clone();
+    session.sync_tokens();
+    serde_json::to_vec_pretty(&VersionedSession { version: SESSION_VERSION, session: &session }).map_err(SystemError::SerializationProblem)
+}
+
+/// Deserializes a saved session, migrating it from older versions of the format.
+pub(crate) fn decode_session(session: &[u8]) -> Result<TwitchClient, SystemError> {
+    let session: Value = serde_json::from_slice(session).map_err(SystemError::DeserializationProblem)?;
+    let Value::Object(mut session) = session else {
+        return Err(SystemError::DeserializationProblem(serde_json::Error::custom("a saved session must be a JSON object")));
+    };
+    let version = match session.remove("version") {
+        None => 1,
+        Some(version) => version.as_u64()
+            .filter(|version| *version >= 1)
+            .ok_or_else(|| SystemError::DeserializationProblem(serde_json::Error::custom(format!("invalid session version {}", version))))?,
+    };
+    if version > SESSION_VERSION {
+        return Err(SystemError::UnsupportedSessionVersion { found: version, supported: SESSION_VERSION });
+    }
+    for migrate in &MIGRATIONS[version as usize - 1..] {
+        migrate(&mut session);
+    }
+    serde_json::from_value(Value::Object(session)).map_err(SystemError::DeserializationProblem)
+}
+
+async fn read_if_exists(path: &Path) -> Result<Option<Vec<u8>>, SystemError> {
+    match fs::read(path).await {
+        Ok(contents) => Ok(Some(contents)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+async fn remove_if_exists(path: &Path) -> Result<(), SystemError> {
+    match fs::remove_file(path).await {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Writes `contents` to a temporary file next to `path`, then renames it over `path`.
+pub(crate) async fn write_atomic(path: &Path, contents: &[u8]) -> Result<(), SystemError> {
+    let file_name = path.file_name().ok_or(SystemError::FileNotFound)?.to_string_lossy();
+    let temp = path.with_file_name(format!(".{}.{}.tmp", file_name, uuid::Uuid::new_v4()));
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    let written = async {
+        let mut file = options.open(&temp).await?;
+        file.write_all(contents).await?;
+        file.sync_all().await?;
+        fs::rename(&temp, path).await
+    };
+    if let Err(e) = written.await {
+        let _ = fs::remove_file(&temp).await;
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use super::*;
+    use crate::{builder::TwitchClientBuilder, client_type::ClientType, endpoints::Endpoints, mock::MockTwitch};
+
+    #[tokio::test]
+    async fn sessions_are_stored_and_renewed_tokens_saved_back() -> Result<(), Box<dyn Error>> {
+        let mock = MockTwitch::start().await?;
+        mock.state().slugs.insert("Marvel Rivals".into(), "marvel-rivals".into());
+        let memory = MemoryStore::new();
+        mock.authorized_client(&ClientType::web()).await?.save_to(&memory).await?;
+
+        let client = TwitchClientBuilder::from_store(Arc::new(memory.clone())).await?.build()?;
+        assert_eq!(client.login.as_deref(), Some("mock_viewer"));
+        mock.state().access_token = "renewed".into();
+        assert_eq!(client.get_slug("Marvel Rivals").await?, "marvel-rivals");
+        let saved = decode_session(&memory.load().await?.unwrap())?;
+        assert_eq!(saved.access_token.as_deref(), Some("renewed"));
+
+        let path = std::env::temp_dir().join(format!("twitch-gql-rs-{}.enc", uuid::Uuid::new_v4()));
+        let encrypted = EncryptedFileStore::new(&path, "hunter2");
+        client.save_to(&encrypted).await?;
+        client.save_to(&encrypted).await?;
+        assert!(!std::fs::read_to_string(&path)?.contains("renewed"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::metadata(&path)?.permissions().mode() & 0o777, 0o600);
+        }
+        let wrong = EncryptedFileStore::new(&path, "hunter3");
+        assert!(matches!(wrong.load().await, Err(SystemError::SessionDecryption(_))));
+        let loaded = TwitchClientBuilder::from_store(Arc::new(encrypted)).await?.build()?;
+        assert_eq!(loaded.access_token.as_deref(), Some("renewed"));
+        std::fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn saved_sessions_are_versioned_and_migrated() -> Result<(), Box<dyn Error>> {
+        let path = std::env::temp_dir().join(format!("twitch-gql-rs-{}.json", uuid::Uuid::new_v4()));
+        let legacy = r#"{"client_id":"id","user_agent":"agent","client_url":"https://www.twitch.tv","device_id":"device","user_id":"1","login":"viewer","access_token":"token"}"#;
+        fs::write(&path, legacy).await?;
+        let client = TwitchClient::load_from_file(&path, &None).await?;
+        assert_eq!((client.access_token.as_deref(), client.refresh_token.as_deref()), (Some("token"), None));
+        assert_eq!(client.endpoints, Endpoints::default());
+        fs::remove_file(&path).await?;
+
+        client.save_file(&path).await?;
+        let saved: Value = serde_json::from_str(&fs::read_to_string(&path).await?)?;
+        assert_eq!(saved["version"], SESSION_VERSION);
+        assert_eq!(TwitchClient::load_from_file(&path, &None).await?.login.as_deref(), Some("viewer"));
+        fs::remove_file(&path).await?;
+
+        fs::write(&path, legacy.replacen('{', r#"{"version":99,"#, 1)).await?;
+        let newer = TwitchClient::load_from_file(&path, &None).await;
+        assert!(matches!(newer, Err(SystemError::UnsupportedSessionVersion { found: 99, .. })));
+        fs::remove_file(&path).await?;
+        Ok(())
+    }
+}
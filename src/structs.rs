@@ -15,6 +15,18 @@ pub struct GameDirectory {
     pub game: Game,
 }
 
+/// Page of streams returned by the game directory.
+#[allow(non_snake_case)]
+#[derive(Deserialize, Serialize, Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct GameDirectoryStreams {
+    pub edges: Vec<GameDirectoryEdge>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Deserialize, Serialize, Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct GameDirectoryEdge {
+    pub node: GameDirectory,
+}
 
 /// Information about the broadcaster (channel owner) for a stream
 #[allow(non_snake_case)]
@@ -0,0 +1,156 @@
+use std::{fmt, sync::{Arc, RwLock}, time::Duration};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::{TwitchClient, api::refresh_access_token, error::{SystemError, TwitchError}, scope::Scope, store::{SessionStore, encode_session}};
+
+/// Access tokens are renewed this long before they expire.
+const REFRESH_MARGIN: TimeDelta = TimeDelta::seconds(60);
+
+/// OAuth tokens issued to a [`TwitchClient`].
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct TokenSet {
+    pub access_token: String,
+    pub refresh_token: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Scopes granted to the access token.
+    #[serde(default)]
+    pub scopes: Vec<Scope>,
+}
+
+impl TokenSet {
+    /// Returns `true` if the access token is expired or about to expire.
+    pub fn is_expiring(&self) -> bool {
+        self.expires_at.is_some_and(|expires_at| Utc::now() + REFRESH_MARGIN >= expires_at)
+    }
+}
+
+/// What Twitch reports about an access token, see [`TwitchClient::validate_session`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionInfo {
+    pub user_id: String,
+    pub login: String,
+    /// Client ID the token was issued to.
+    pub client_id: String,
+    pub scopes: Vec<Scope>,
+    /// Remaining lifetime of the token, or `None` if it does not expire.
+    pub expires_in: Option<Duration>,
+}
+
+type RefreshHandler = dyn Fn(&TokenSet) + Send + Sync;
+
+/// Token changes made at runtime, seen by every clone of a client.
+#[derive(Debug, Clone, Default)]
+pub(crate) enum SharedTokens {
+    /// The client's own `access_token`, `refresh_token` and `token_expires_at` fields are current.
+    #[default]
+    Unchanged,
+    Renewed(TokenSet),
+    /// The session was logged out: the tokens in the fields of other clones are revoked.
+    Cleared,
+}
+
+/// Tokens renewed or cleared at runtime, shared between clones of a client.
+#[derive(Clone, Default)]
+pub(crate) struct TokenState {
+    shared: Arc<RwLock<SharedTokens>>,
+    refreshing: Arc<Mutex<()>>,
+    handlers: Vec<Arc<RefreshHandler>>,
+    store: Option<Arc<dyn SessionStore>>,
+}
+
+impl fmt::Debug for TokenState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TokenState")
+            .field("tokens", &match *self.shared.read().unwrap() {
+                SharedTokens::Unchanged => "unchanged",
+                SharedTokens::Renewed(_) => "renewed",
+                SharedTokens::Cleared => "cleared",
+            })
+            .field("store", &self.store.is_some())
+            .finish()
+    }
+}
+
+impl TokenState {
+    pub fn shared(&self) -> SharedTokens {
+        self.shared.read().unwrap().clone()
+    }
+
+    pub fn set(&self, tokens: Option<TokenSet>) {
+        *self.shared.write().unwrap() = match tokens {
+            Some(tokens) => SharedTokens::Renewed(tokens),
+            None => SharedTokens::Unchanged,
+        };
+    }
+
+    /// Marks the tokens as revoked for every clone.
+    pub fn clear(&self) {
+        *self.shared.write().unwrap() = SharedTokens::Cleared;
+    }
+
+    pub fn add_handler(&mut self, handler: Arc<RefreshHandler>) {
+        self.handlers.push(handler);
+    }
+
+    pub fn set_store(&mut self, store: Arc<dyn SessionStore>) {
+        self.store = Some(store);
+    }
+
+    /// Writes the session to the attached store, if any.
+    pub async fn save(&self, twitch: &TwitchClient) -> Result<(), SystemError> {
+        match &self.store {
+            Some(store) => store.save(&encode_session(twitch)?).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Removes the session from the attached store, if any.
+    pub async fn delete(&self) -> Result<(), SystemError> {
+        match &self.store {
+            Some(store) => store.delete().await,
+            None => Ok(()),
+        }
+    }
+
+    /// Renews the access token if it expires soon.
+    pub async fn refresh_if_expiring(&self, twitch: &TwitchClient) -> Result<(), TwitchError> {
+        match twitch.tokens() {
+            Some(tokens) if tokens.refresh_token.is_some() && tokens.is_expiring() => self.refresh(twitch, Some(&tokens.access_token)).await.map(|_| ()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Renews the access token, unless it already changed from `stale`.
+    ///
+    /// Concurrent callers wait for a single refresh request.
+    pub async fn refresh(&self, twitch: &TwitchClient, stale: Option<&str>) -> Result<TokenSet, TwitchError> {
+        let _refreshing = self.refreshing.lock().await;
+        let current = twitch.tokens().ok_or_else(|| TwitchError::TokenRefreshFailed("no access token".into()))?;
+        if stale.is_some_and(|stale| stale != current.access_token) {
+            return Ok(current);
+        }
+        let refresh_token = current.refresh_token.ok_or_else(|| TwitchError::TokenRefreshFailed("no refresh token".into()))?;
+        let mut tokens = refresh_access_token(twitch, &refresh_token).await?;
+        // Twitch may not rotate the refresh token, nor repeat the scopes.
+        if tokens.refresh_token.is_none() {
+            tokens.refresh_token = Some(refresh_token);
+        }
+        if tokens.scopes.is_empty() {
+            tokens.scopes = current.scopes;
+        }
+        self.set(Some(tokens.clone()));
+        for handler in &self.handlers {
+            handler(&tokens);
+        }
+        // The new tokens are already in use, so a failed write only loses them for the next run.
+        #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+        if let Err(e) = self.save(twitch).await {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(error = %e, "failed to save renewed tokens");
+        }
+        Ok(tokens)
+    }
+}